@@ -0,0 +1,38 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Embeds the git commit and build time, so that reports can be traced back to the source.
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=DEBUGGER_GIT_HASH={}", git_hash);
+
+    // Rebuild when the checked out commit changes: HEAD moves to another branch, or the branch
+    // gets a new commit (its loose ref, or packed-refs after a gc). Once any rerun-if-changed is
+    // set, cargo no longer reruns on every source change, so the sources are listed too.
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/packed-refs");
+    if let Some(head_ref) = std::fs::read_to_string(".git/HEAD").ok().and_then(|head| {
+        head.strip_prefix("ref: ")
+            .map(|head_ref| head_ref.trim().to_string())
+    }) {
+        println!("cargo:rerun-if-changed=.git/{}", head_ref);
+    }
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    println!(
+        "cargo:rustc-env=DEBUGGER_BUILD_TIMESTAMP={}",
+        build_timestamp
+    );
+}
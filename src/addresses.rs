@@ -2,38 +2,103 @@ use alloy::primitives::{address, Address, U256};
 
 use colored::Colorize;
 use lazy_static::lazy_static;
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use std::sync::RwLock;
 
-// Define the global singleton map using lazy_static and RwLock for safe concurrent access
-lazy_static! {
-    static ref ADDRESS_MAPPING: RwLock<HashMap<Address, String>> = {
-        let initial_entries = vec![
-            (
-                address!("0000000000000000000000000000000000008006"),
-                "Deployer",
-            ),
-            (
-                address!("0000000000000000000000000000000000010002"),
-                "Bridgehub",
-            ),
-            (
-                address!("0000000000000000000000000000000000010003"),
-                "Shared Bridge",
-            ),
+// Predeployed (system) contracts and sentinels that the tool relies on. The standard ones are
+// used on all the known networks, deployments that differ can override them with
+// --system-addresses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SystemAddresses {
+    pub l2_deployer: Address,
+    // Bridgehub on every ZK chain (including the gateway).
+    pub l2_bridgehub: Address,
+    pub l2_shared_bridge: Address,
+    pub settlement_layer_relay_sender: Address,
+    // Address used by the native token vault to represent ETH.
+    pub eth_token: Address,
+}
+
+impl Default for SystemAddresses {
+    fn default() -> Self {
+        Self {
+            l2_deployer: address!("0000000000000000000000000000000000008006"),
+            l2_bridgehub: address!("0000000000000000000000000000000000010002"),
+            l2_shared_bridge: address!("0000000000000000000000000000000000010003"),
+            settlement_layer_relay_sender: address!("1111111111111111111111111111111111111111"),
+            eth_token: address!("0000000000000000000000000000000000000001"),
+        }
+    }
+}
+
+// Overrides loaded from a JSON file - only the addresses that are present are replaced.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SystemAddressOverrides {
+    pub l2_deployer: Option<Address>,
+    pub l2_bridgehub: Option<Address>,
+    pub l2_shared_bridge: Option<Address>,
+    pub settlement_layer_relay_sender: Option<Address>,
+    pub eth_token: Option<Address>,
+}
+
+impl SystemAddresses {
+    pub fn with_overrides(self, overrides: &SystemAddressOverrides) -> Self {
+        Self {
+            l2_deployer: overrides.l2_deployer.unwrap_or(self.l2_deployer),
+            l2_bridgehub: overrides.l2_bridgehub.unwrap_or(self.l2_bridgehub),
+            l2_shared_bridge: overrides.l2_shared_bridge.unwrap_or(self.l2_shared_bridge),
+            settlement_layer_relay_sender: overrides
+                .settlement_layer_relay_sender
+                .unwrap_or(self.settlement_layer_relay_sender),
+            eth_token: overrides.eth_token.unwrap_or(self.eth_token),
+        }
+    }
+
+    fn names(&self) -> [(Address, &'static str); 4] {
+        [
+            (self.l2_deployer, "Deployer"),
+            (self.l2_bridgehub, "Bridgehub"),
+            (self.l2_shared_bridge, "Shared Bridge"),
             (
-                address!("1111111111111111111111111111111111111111"),
+                self.settlement_layer_relay_sender,
                 "SettlementLayerRelaySender",
             ),
-        ];
+        ]
+    }
+}
+
+pub fn load_system_address_overrides(path: &Path) -> eyre::Result<SystemAddressOverrides> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+// Define the global singleton map using lazy_static and RwLock for safe concurrent access
+lazy_static! {
+    static ref SYSTEM_ADDRESSES: RwLock<SystemAddresses> = RwLock::new(SystemAddresses::default());
+    static ref ADDRESS_MAPPING: RwLock<HashMap<Address, String>> = {
         let mut m = HashMap::new();
-        for entry in initial_entries {
+        for entry in SystemAddresses::default().names() {
             m.insert(entry.0, entry.1.to_string());
         }
 
         RwLock::new(m)
     };
 }
+
+pub fn system_addresses() -> SystemAddresses {
+    *SYSTEM_ADDRESSES.read().unwrap()
+}
+
+// Selects the system addresses for the run (and names them in the output).
+pub fn set_system_addresses(addresses: SystemAddresses) {
+    for (address, name) in addresses.names() {
+        add_address_name(address, name.to_string());
+    }
+    *SYSTEM_ADDRESSES.write().unwrap() = addresses;
+}
 pub fn add_address_name(key: Address, value: String) {
     let mut map = ADDRESS_MAPPING.write().unwrap(); // Get write access to the map
     map.insert(key, value);
@@ -58,3 +123,29 @@ pub fn address_to_human(address: &Address) -> String {
         format!("{}", address)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_only_the_given_addresses() {
+        let overrides: SystemAddressOverrides =
+            serde_json::from_str(r#"{"eth_token": "0x000000000000000000000000000000000000800a"}"#)
+                .unwrap();
+
+        let addresses = SystemAddresses::default().with_overrides(&overrides);
+
+        assert_eq!(
+            addresses.eth_token,
+            address!("000000000000000000000000000000000000800a")
+        );
+        assert_eq!(
+            addresses.l2_bridgehub,
+            SystemAddresses::default().l2_bridgehub
+        );
+        assert!(
+            serde_json::from_str::<SystemAddressOverrides>(r#"{"bridgehub": "0x00"}"#).is_err()
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// Rendering of token amounts (balances, transaction values) in the console output and in the
+// report's `formatted` fields. Raw values in the report are never affected.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use alloy::primitives::U256;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AmountFormat {
+    // Ether with all 18 decimals, e.g. `1.500000000000000000`.
+    Ether,
+    // Plain wei, e.g. `1500000000000000000`.
+    Wei,
+    // Wei with `_` thousands separators, e.g. `1_500_000_000_000_000_000`.
+    GroupedWei,
+}
+
+// Set with --amount-format; if not set, every place keeps its own default format.
+static AMOUNT_FORMAT: Mutex<Option<AmountFormat>> = Mutex::new(None);
+
+// Set with --formatted-symbols: the report's `formatted` balances carry the symbol too.
+static SYMBOLS_IN_REPORT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_amount_format(format: Option<AmountFormat>) {
+    *AMOUNT_FORMAT.lock().unwrap() = format;
+}
+
+pub fn set_symbols_in_report(symbols: bool) {
+    SYMBOLS_IN_REPORT.store(symbols, Ordering::Relaxed);
+}
+
+pub fn symbols_in_report() -> bool {
+    SYMBOLS_IN_REPORT.load(Ordering::Relaxed)
+}
+
+// Formats the amount in the configured format, or in `default` if none was configured.
+pub fn format_amount(wei: U256, default: AmountFormat) -> String {
+    let format = AMOUNT_FORMAT.lock().unwrap().unwrap_or(default);
+    format_amount_as(wei, format)
+}
+
+pub fn format_amount_as(wei: U256, format: AmountFormat) -> String {
+    match format {
+        AmountFormat::Ether => format_ether(wei),
+        AmountFormat::Wei => wei.to_string(),
+        AmountFormat::GroupedWei => format_integer_with_underscores(&wei.to_string()),
+    }
+}
+
+// Formats a token balance in whole tokens of the given decimals (18 if unknown), unless a wei
+// format was configured. The symbol is only appended to whole tokens of known decimals - next
+// to an amount scaled by the wrong decimals it would look authoritative, e.g. `0.5 USDC`.
+pub fn format_token_amount(amount: U256, decimals: Option<u8>, symbol: Option<&str>) -> String {
+    let format = AMOUNT_FORMAT.lock().unwrap().unwrap_or(AmountFormat::Ether);
+    match (format, decimals) {
+        (AmountFormat::Ether, Some(decimals)) => {
+            with_symbol(format_units(amount, decimals.into()), symbol)
+        }
+        (AmountFormat::Ether, None) => format_ether(amount),
+        (format, _) => format_amount_as(amount, format),
+    }
+}
+
+// Appends the token symbol to a formatted amount (when it is known).
+fn with_symbol(amount: String, symbol: Option<&str>) -> String {
+    match symbol {
+        Some(symbol) => format!("{} {}", amount, symbol),
+        None => amount,
+    }
+}
+
+fn format_ether(wei: U256) -> String {
+    format_units(wei, 18)
+}
+
+// The amount with a decimal point `decimals` places from the end.
+fn format_units(amount: U256, decimals: usize) -> String {
+    let digits = amount.to_string();
+    let len = digits.len();
+
+    if decimals == 0 {
+        digits
+    } else if len > decimals {
+        format!(
+            "{}.{}",
+            &digits[..len - decimals],
+            &digits[len - decimals..]
+        )
+    } else {
+        // If the string is shorter than the decimals, pad with zeros
+        format!("0.{:0>width$}", digits, width = decimals)
+    }
+}
+
+fn format_integer_with_underscores(input: &str) -> String {
+    let reversed_input: String = input.chars().rev().collect();
+
+    // Insert underscores every three characters
+    let mut formatted = String::new();
+    for (index, char) in reversed_input.chars().enumerate() {
+        if index % 3 == 0 && index != 0 {
+            formatted.push('_');
+        }
+        formatted.push(char);
+    }
+
+    // Reverse the formatted string to correct the order
+    formatted.chars().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ether(wei: U256) -> String {
+        format_amount_as(wei, AmountFormat::Ether)
+    }
+
+    #[test]
+    fn formats_zero() {
+        assert_eq!(ether(U256::ZERO), "0.000000000000000000");
+    }
+
+    #[test]
+    fn formats_amounts_below_one_ether() {
+        assert_eq!(ether(U256::from(1)), "0.000000000000000001");
+        assert_eq!(
+            ether(U256::from(500_000_000_000_000_000u64)),
+            "0.500000000000000000"
+        );
+    }
+
+    #[test]
+    fn formats_exactly_one_ether() {
+        assert_eq!(
+            ether(U256::from(1_000_000_000_000_000_000u64)),
+            "1.000000000000000000"
+        );
+    }
+
+    #[test]
+    fn formats_large_amounts() {
+        let wei = U256::from(123_456u64) * U256::from(10).pow(U256::from(18)) + U256::from(7);
+        assert_eq!(ether(wei), "123456.000000000000000007");
+    }
+
+    #[test]
+    fn formats_token_amounts_with_their_decimals() {
+        let usdc = U256::from(1_000_000_000u64);
+        assert_eq!(
+            format_token_amount(usdc, Some(6), Some("USDC")),
+            "1000.000000 USDC"
+        );
+        assert_eq!(format_token_amount(usdc, Some(6), None), "1000.000000");
+        assert_eq!(
+            format_token_amount(U256::from(7), Some(0), Some("X")),
+            "7 X"
+        );
+        // Without the decimals the amount can't be trusted to be in whole tokens.
+        assert_eq!(
+            format_token_amount(usdc, None, Some("USDC")),
+            "0.000000001000000000"
+        );
+    }
+
+    #[test]
+    fn formats_wei() {
+        let wei = U256::from(1_234_567u64);
+        assert_eq!(format_amount_as(wei, AmountFormat::Wei), "1234567");
+        assert_eq!(format_amount_as(wei, AmountFormat::GroupedWei), "1_234_567");
+        assert_eq!(
+            format_amount_as(U256::from(123), AmountFormat::GroupedWei),
+            "123"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+// Comparison of the bridge balances against a previous report (--diff), with alerts for the
+// balances that dropped by more than --alert-balance-drop percent.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+
+// (chain id, token, layer) - a token can be held both on L1 and on the gateway.
+pub type BalanceKey = (u64, String, String);
+
+// The parts of a previous report that are needed for the comparison.
+#[derive(Deserialize)]
+struct PreviousReport {
+    l1_balances: Vec<PreviousChainBalances>,
+}
+
+#[derive(Deserialize)]
+struct PreviousChainBalances {
+    chain_id: u64,
+    tokens: Vec<PreviousTokenBalance>,
+}
+
+#[derive(Deserialize)]
+struct PreviousTokenBalance {
+    token: String,
+    layer: String,
+    raw_wei: String,
+}
+
+pub fn load_previous_balances(path: &Path) -> eyre::Result<BTreeMap<BalanceKey, U256>> {
+    let report: PreviousReport = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|err| eyre::eyre!("Invalid previous report {}: {}", path.display(), err))?;
+    let mut balances = BTreeMap::new();
+    for chain in report.l1_balances {
+        for token in chain.tokens {
+            let amount = token.raw_wei.parse().map_err(|err| {
+                eyre::eyre!(
+                    "Invalid balance '{}' in {}: {}",
+                    token.raw_wei,
+                    path.display(),
+                    err
+                )
+            })?;
+            balances.insert((chain.chain_id, token.token, token.layer), amount);
+        }
+    }
+    Ok(balances)
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct BalanceChange {
+    pub chain_id: u64,
+    pub token: String,
+    pub layer: String,
+    pub previous_raw_wei: String,
+    pub current_raw_wei: String,
+    // Percentage of the previous balance that is gone, None if the balance didn't drop.
+    pub drop_percent: Option<f64>,
+    // Whether the drop is above --alert-balance-drop.
+    pub alert: bool,
+}
+
+// Percentage of `previous` that `current` is below it (with 2 decimals), None if it isn't.
+fn drop_percent(previous: U256, current: U256) -> Option<f64> {
+    if current >= previous {
+        return None;
+    }
+    let basis_points = (previous - current) * U256::from(10_000) / previous;
+    Some(basis_points.to::<u64>() as f64 / 100.0)
+}
+
+// Balances that changed between the reports. Balances missing from either of them (e.g. the
+// collection failed) can't be compared and are skipped.
+pub fn diff_balances(
+    previous: &BTreeMap<BalanceKey, U256>,
+    current: &BTreeMap<BalanceKey, U256>,
+    alert_drop_percent: Option<f64>,
+) -> Vec<BalanceChange> {
+    current
+        .iter()
+        .filter_map(|(key, current)| {
+            let previous = previous.get(key)?;
+            if previous == current {
+                return None;
+            }
+            let drop_percent = drop_percent(*previous, *current);
+            let alert = match (drop_percent, alert_drop_percent) {
+                (Some(dropped), Some(threshold)) => dropped > threshold,
+                _ => false,
+            };
+            Some(BalanceChange {
+                chain_id: key.0,
+                token: key.1.clone(),
+                layer: key.2.clone(),
+                previous_raw_wei: previous.to_string(),
+                current_raw_wei: current.to_string(),
+                drop_percent,
+                alert,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(chain_id: u64, token: &str) -> BalanceKey {
+        (chain_id, token.to_string(), "l1".to_string())
+    }
+
+    #[test]
+    fn computes_drops() {
+        assert_eq!(drop_percent(U256::from(200), U256::from(150)), Some(25.0));
+        assert_eq!(drop_percent(U256::from(3), U256::from(2)), Some(33.33));
+        assert_eq!(drop_percent(U256::from(100), U256::from(100)), None);
+        assert_eq!(drop_percent(U256::ZERO, U256::from(5)), None);
+    }
+
+    #[test]
+    fn flags_drops_above_the_threshold() {
+        let previous = BTreeMap::from([
+            (key(324, "ETH-a"), U256::from(1000)),
+            (key(324, "USDC-b"), U256::from(1000)),
+            (key(388, "ETH-a"), U256::from(1000)),
+            (key(500, "ETH-a"), U256::from(1000)),
+        ]);
+        let current = BTreeMap::from([
+            (key(324, "ETH-a"), U256::from(400)),
+            (key(324, "USDC-b"), U256::from(950)),
+            (key(388, "ETH-a"), U256::from(2000)),
+            (key(501, "ETH-a"), U256::from(1)),
+        ]);
+
+        let changes = diff_balances(&previous, &current, Some(10.0));
+
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.chain_id, change.drop_percent, change.alert))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (324, Some(60.0), true),
+                (324, Some(5.0), false),
+                (388, None, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_balances_from_a_report() {
+        let path = std::env::temp_dir().join(format!("previous-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"network": "mainnet", "l1_balances": [{"chain_id": 324, "tokens": [
+                {"token": "ETH-a", "layer": "gateway", "raw_wei": "42", "formatted": "x"}]}]}"#,
+        )
+        .unwrap();
+
+        let balances = load_previous_balances(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            balances,
+            BTreeMap::from([(
+                (324, "ETH-a".to_string(), "gateway".to_string()),
+                U256::from(42)
+            )])
+        );
+    }
+}
@@ -1,19 +1,25 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 
-use crate::l1_asset_router::{AssetHandler, L1AssetRouter};
+use crate::addresses::{add_address_name, address_to_human, system_addresses};
+use crate::chain_names::chain_to_human;
+use crate::error::DebuggerError;
+use crate::l1_asset_router::{resolve_token_metadata, AssetHandler, L1AssetRouter, TokenMetadata};
 use crate::l2_asset_router::L2AssetRouter;
-use crate::sequencer::Sequencer;
-use crate::statetransition::StateTransition;
-use crate::stm::ChainTypeManager;
-use crate::utils::get_human_name_for;
+use crate::sequencer::{Sequencer, SequencerProvider};
+use crate::statetransition::{get_settlement_layer, StateTransition};
+use crate::stm::{ChainTypeManager, MigrationRecord};
+use crate::utils::{
+    format_implementation, get_all_events, get_human_name_for, get_proxy_implementation,
+    ContractCode,
+};
 use alloy::primitives::{Address, FixedBytes, U256};
-use alloy::providers::{Provider, RootProvider};
+use alloy::providers::Provider;
 use alloy::sol;
-use alloy::transports::http::{Client, Http};
+use alloy::sol_types::SolEvent;
 use colored::Colorize;
 
-use futures::future::join_all;
+use futures::{stream, StreamExt};
 use serde::Serialize;
 
 fn format_address(value: Address) -> String {
@@ -24,24 +30,141 @@ fn format_fixed_bytes(value: FixedBytes<32>) -> String {
     format!("{:#x}", value)
 }
 
+// Mismatches between the bridgehub and the bridgehub its shared bridge and CTMs
+// (address, bridgehub) point to - usually a misconfiguration, or a stale bridgehub.
+fn wiring_warnings(
+    bridgehub: Address,
+    shared_bridge: Address,
+    shared_bridge_bridgehub: Option<Address>,
+    ctms: &[(Address, Address)],
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(pointed) = shared_bridge_bridgehub.filter(|pointed| *pointed != bridgehub) {
+        warnings.push(format!(
+            "Shared bridge {} points to bridgehub {}, not {}",
+            shared_bridge, pointed, bridgehub
+        ));
+    }
+    for (ctm, pointed) in ctms {
+        if *pointed != bridgehub {
+            warnings.push(format!(
+                "CTM {} points to bridgehub {}, not {}",
+                ctm, pointed, bridgehub
+            ));
+        }
+    }
+    warnings
+}
+
 #[derive(Serialize)]
 pub struct BridgehubSummary {
     pub address: String,
+    pub implementation: Option<String>,
+    pub contract_code_hash: String,
+    pub contract_code_size: usize,
     pub shared_bridge: String,
+    pub shared_bridge_implementation: Option<String>,
+    pub shared_bridge_code_hash: String,
+    pub shared_bridge_code_size: usize,
     pub ctm_deployer: String,
+    // Ownership of the bridgehub and the shared bridge (null if the getter reverted).
+    pub owner: Option<String>,
+    pub admin: Option<String>,
+    pub shared_bridge_owner: Option<String>,
     pub known_chains: Vec<u64>,
+    pub ctm_unknown_chains: Vec<u64>,
     pub ctms: Option<Vec<ChainTypeManagerSummary>>,
     pub asset_router: AssetRouterSummary,
+    pub asset_registrations: Vec<AssetRegistrationSummary>,
+    // Empty if the CTMs weren't resolved.
+    pub ctm_asset_map: Vec<CtmAssetMappingSummary>,
+    // Shared bridge / CTMs that don't point back to this bridgehub.
+    pub consistency_warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CtmAssetMappingSummary {
+    pub ctm_address: String,
+    pub asset_id: String,
+    pub human_name: String,
+    pub chain_ids: Vec<u64>,
+}
+
+impl From<&CtmAssetMapping> for CtmAssetMappingSummary {
+    fn from(value: &CtmAssetMapping) -> Self {
+        Self {
+            ctm_address: format_address(value.ctm_address),
+            asset_id: format_fixed_bytes(value.asset_id),
+            human_name: value.human_name.clone(),
+            chain_ids: value.chain_ids.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AssetRegistrationSummary {
+    pub asset_info: String,
+    pub asset_address: String,
+    pub additional_data: String,
+    pub sender: String,
+    pub block: Option<u64>,
+    pub matches_current_state: Option<bool>,
+}
+
+impl From<&AssetRegistration> for AssetRegistrationSummary {
+    fn from(value: &AssetRegistration) -> Self {
+        Self {
+            asset_info: format_fixed_bytes(value.asset_info),
+            asset_address: format_address(value.asset_address),
+            additional_data: format_fixed_bytes(value.additional_data),
+            sender: format_address(value.sender),
+            block: value.block,
+            matches_current_state: value.matches_current_state,
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct ChainTypeManagerSummary {
     pub address: String,
+    pub implementation: Option<String>,
     pub bridgehub: String,
     pub admin: String,
     pub owner: String,
     pub asset_id: String,
     pub asset_name: String,
+    pub migrations: Vec<MigrationRecordSummary>,
+}
+
+#[derive(Serialize)]
+pub struct MigrationRecordSummary {
+    pub chain_id: String,
+    pub asset_id: String,
+    pub zk_chain: String,
+    pub block: Option<u64>,
+}
+
+impl From<&MigrationRecord> for MigrationRecordSummary {
+    fn from(value: &MigrationRecord) -> Self {
+        Self {
+            chain_id: value.chain_id.to_string(),
+            asset_id: format_fixed_bytes(value.asset_id),
+            zk_chain: format_address(value.zk_chain),
+            block: value.block,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BridgehubChainDetailsSummary {
+    pub ctm: String,
+    pub ctm_asset_id: String,
+    pub hyperchain: String,
+    // null if the baseToken query reverted, the zero address if the base token is unset.
+    pub base_token: Option<String>,
+    pub base_token_name: Option<String>,
+    pub base_token_symbol: Option<String>,
+    pub validator_timelock: String,
 }
 
 #[derive(Serialize)]
@@ -64,6 +187,7 @@ pub enum AssetRouterSummary {
     },
     L2 {
         address: String,
+        l1_asset_router: Option<String>,
     },
 }
 
@@ -71,11 +195,31 @@ impl From<&ChainTypeManager> for ChainTypeManagerSummary {
     fn from(value: &ChainTypeManager) -> Self {
         ChainTypeManagerSummary {
             address: format_address(value.address),
+            implementation: value.implementation.map(format_address),
             bridgehub: format_address(value.bridgehub),
             admin: format_address(value.admin),
             owner: format_address(value.owner),
             asset_id: format_fixed_bytes(value.asset_id),
             asset_name: value.asset_name.clone(),
+            migrations: value
+                .migrations
+                .iter()
+                .map(MigrationRecordSummary::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&BridgehubChainDetails> for BridgehubChainDetailsSummary {
+    fn from(value: &BridgehubChainDetails) -> Self {
+        BridgehubChainDetailsSummary {
+            ctm: format_address(value.stm_address),
+            ctm_asset_id: format_fixed_bytes(value.stm_asset_id),
+            hyperchain: format_address(value.st_address),
+            base_token: value.base_token_address.map(format_address),
+            base_token_name: value.base_token.as_ref().map(|token| token.name.clone()),
+            base_token_symbol: value.base_token.as_ref().map(|token| token.symbol.clone()),
+            validator_timelock: format_address(value.validator_timelock_address),
         }
     }
 }
@@ -137,7 +281,20 @@ sol! {
 
         function getAllZKChainChainIDs() external view returns (uint256[] memory);
 
+        mapping(uint256 chainId => uint256) public settlementLayer;
+        mapping(uint256 chainId => bool) public whitelistedSettlementLayers;
+
         address public l1CtmDeployer;
+
+        function owner() external view returns (address);
+        function admin() external view returns (address);
+    }
+}
+
+sol! {
+    #[sol(rpc)]
+    contract IOwnable {
+        function owner() external view returns (address);
     }
 }
 
@@ -147,7 +304,9 @@ sol! {
 pub struct BridgehubChainDetails {
     pub stm_address: Address,
     pub st_address: Address,
-    pub base_token_address: Address,
+    // None if the baseToken query reverted (Some(ZERO) means that it is unset).
+    pub base_token_address: Option<Address>,
+    pub base_token: Option<TokenMetadata>,
     pub validator_timelock_address: Address,
     pub stm_asset_id: FixedBytes<32>,
 }
@@ -161,7 +320,23 @@ impl Display for BridgehubChainDetails {
         )?;
         writeln!(f, "    CTM:                {}", self.stm_address)?;
         writeln!(f, "    ST:                 {}", self.st_address)?;
-        writeln!(f, "    Base Token:         {}", self.base_token_address)?;
+        match (&self.base_token, self.base_token_address) {
+            (_, None) => writeln!(
+                f,
+                "    Base Token:         {}",
+                "unavailable (revert)".yellow()
+            )?,
+            (_, Some(Address::ZERO)) => {
+                writeln!(f, "    Base Token:         {}", "unset".yellow())?
+            }
+            (Some(token), Some(address)) => writeln!(
+                f,
+                "    Base Token:         {} ({})",
+                token.symbol.bold(),
+                address
+            )?,
+            (None, Some(address)) => writeln!(f, "    Base Token:         {}", address)?,
+        }
         writeln!(
             f,
             "    Validator timelock: {}",
@@ -171,6 +346,88 @@ impl Display for BridgehubChainDetails {
     }
 }
 
+// CTM with its asset id (ctmAssetIdFromAddress) and the chains that it manages - the
+// relationships that asset based chain migrations rely on.
+#[derive(Debug, PartialEq)]
+pub struct CtmAssetMapping {
+    pub ctm_address: Address,
+    pub asset_id: FixedBytes<32>,
+    pub human_name: String,
+    pub chain_ids: Vec<u64>,
+}
+
+// Ordered by the human name, like the CTMs in the summary.
+fn build_ctm_asset_map(
+    ctms: &[ChainTypeManager],
+    ctm_chains: &HashMap<Address, Vec<u64>>,
+) -> Vec<CtmAssetMapping> {
+    let mut result: Vec<_> = ctms
+        .iter()
+        .map(|ctm| {
+            let mut chain_ids = ctm_chains.get(&ctm.address).cloned().unwrap_or_default();
+            chain_ids.sort_unstable();
+            CtmAssetMapping {
+                ctm_address: ctm.address,
+                asset_id: ctm.asset_id,
+                human_name: ctm.asset_name.clone(),
+                chain_ids,
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| a.human_name.cmp(&b.human_name));
+    result
+}
+
+// Decoded AssetRegistered event from the bridgehub.
+pub struct AssetRegistration {
+    pub asset_info: FixedBytes<32>,
+    pub asset_address: Address,
+    pub additional_data: FixedBytes<32>,
+    pub sender: Address,
+    pub block: Option<u64>,
+    // Whether the bridgehub still maps the asset address to the same asset id
+    // (None if the current state couldn't be fetched).
+    pub matches_current_state: Option<bool>,
+}
+
+impl Display for AssetRegistration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.matches_current_state {
+            Some(true) => "OK".green(),
+            Some(false) => "MISMATCH".red(),
+            None => "UNKNOWN".yellow(),
+        };
+        write!(
+            f,
+            "    {} -> {} (block {}) [{}]",
+            get_human_name_for(self.asset_info).bold(),
+            self.asset_address,
+            self.block
+                .map_or("unknown".to_string(), |block| block.to_string()),
+            status
+        )
+    }
+}
+
+// Settlement layer that a chain should be queried on, with its state transition address there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChainLocation {
+    L1 { state_transition: Address },
+    Gateway { state_transition: Address },
+    Unknown,
+}
+
+impl Display for ChainLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            ChainLocation::L1 { .. } => "l1",
+            ChainLocation::Gateway { .. } => "gateway",
+            ChainLocation::Unknown => "unknown",
+        };
+        write!(f, "{}", label)
+    }
+}
+
 pub enum AssetRouter {
     L1(L1AssetRouter),
     L2(L2AssetRouter),
@@ -200,23 +457,70 @@ impl AssetRouter {
     }
 }
 
+// What Bridgehub::new_with_options should load besides the bridgehub itself.
+#[derive(Clone, Copy, Debug)]
+pub struct BridgehubOptions {
+    // Look up the CTM of every chain and load each distinct one (several calls per CTM).
+    // If not set, `ctms` is None.
+    pub resolve_ctms: bool,
+    // Maximum number of CTMs loaded at once.
+    pub concurrency: usize,
+}
+
 /// Bridgehub is the main coordination contract on each chain.
 /// the 'main main' bridgehub is located on L1.
 pub struct Bridgehub {
     pub address: Address,
+    pub implementation: Option<Address>,
+    pub code: ContractCode,
     pub shared_bridge: Address,
+    pub shared_bridge_implementation: Option<Address>,
+    pub shared_bridge_code: ContractCode,
     pub known_chains: HashSet<u64>,
+    // Chains whose CTM couldn't be fetched (the call reverted).
+    pub ctm_unknown_chains: Vec<u64>,
     pub ctms: Option<Vec<ChainTypeManager>>,
-    provider: RootProvider<Http<Client>>,
+    // Chains managed by each CTM (from chainTypeManager), empty if the CTMs weren't resolved.
+    pub ctm_chains: HashMap<Address, Vec<u64>>,
+    provider: SequencerProvider,
     pub ctm_deployer: Address,
+    // None if the getter reverted (e.g. older deployments).
+    pub owner: Option<Address>,
+    pub admin: Option<Address>,
+    pub shared_bridge_owner: Option<Address>,
 
     pub asset_router: AssetRouter,
 }
 
+fn format_owner(value: Option<Address>) -> String {
+    value.map_or("unavailable".yellow().to_string(), |address| {
+        address_to_human(&address)
+    })
+}
+
 impl Display for Bridgehub {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "   Bridgehub at          {}", self.address,)?;
+        writeln!(
+            f,
+            "     Implementation:     {}",
+            format_implementation(self.implementation)
+        )?;
+        writeln!(f, "     Code:               {}", self.code)?;
+        writeln!(f, "     Owner:              {}", format_owner(self.owner))?;
+        writeln!(f, "     Admin:              {}", format_owner(self.admin))?;
         writeln!(f, "   Shared bridge:        {}", self.shared_bridge)?;
+        writeln!(
+            f,
+            "     Implementation:     {}",
+            format_implementation(self.shared_bridge_implementation)
+        )?;
+        writeln!(f, "     Code:               {}", self.shared_bridge_code)?;
+        writeln!(
+            f,
+            "     Owner:              {}",
+            format_owner(self.shared_bridge_owner)
+        )?;
         writeln!(f, "   CTM deployer (on L1): {}", self.ctm_deployer)?;
         if let Some(ctms) = &self.ctms {
             writeln!(f, "   CTMS: {}", ctms.len())?;
@@ -224,19 +528,34 @@ impl Display for Bridgehub {
             for stm in ctms {
                 stm.detailed_fmt(f, 3)?;
             }
+
+            writeln!(f, "   CTM asset ids:")?;
+            for mapping in self.ctm_asset_map() {
+                writeln!(
+                    f,
+                    "     {} {} -> {} (chains: {:?})",
+                    mapping.human_name.bold(),
+                    mapping.asset_id,
+                    mapping.ctm_address,
+                    mapping.chain_ids
+                )?;
+            }
         }
 
         writeln!(f, "   === Asset router")?;
         self.asset_router.detailed_fmt(f, 3)?;
 
+        for warning in self.consistency_warnings() {
+            writeln!(f, "   {} {}", "[WARN]".yellow(), warning)?;
+        }
+
         Ok(())
     }
 }
 
 impl Bridgehub {
     pub fn to_summary(&self) -> BridgehubSummary {
-        let mut known_chains: Vec<u64> = self.known_chains.iter().copied().collect();
-        known_chains.sort_unstable();
+        let known_chains = self.sorted_known_chains();
 
         let ctms = self.ctms.as_ref().map(|ctms| {
             let mut summaries: Vec<_> = ctms.iter().map(ChainTypeManagerSummary::from).collect();
@@ -246,9 +565,8 @@ impl Bridgehub {
 
         let asset_router = match &self.asset_router {
             AssetRouter::L1(router) => {
-                let mut assets: Vec<_> = router.registered_assets.values().collect();
-                assets.sort_by(|a, b| a.name().cmp(&b.name()));
-                let registered_assets = assets
+                let registered_assets = router
+                    .sorted_assets()
                     .into_iter()
                     .map(RegisteredAssetSummary::from)
                     .collect();
@@ -261,60 +579,210 @@ impl Bridgehub {
             }
             AssetRouter::L2(router) => AssetRouterSummary::L2 {
                 address: format_address(router.address),
+                l1_asset_router: router.l1_router.map(format_address),
             },
         };
 
         BridgehubSummary {
             address: format_address(self.address),
+            implementation: self.implementation.map(format_address),
+            contract_code_hash: format_fixed_bytes(self.code.hash),
+            contract_code_size: self.code.size,
             shared_bridge: format_address(self.shared_bridge),
+            shared_bridge_implementation: self.shared_bridge_implementation.map(format_address),
+            shared_bridge_code_hash: format_fixed_bytes(self.shared_bridge_code.hash),
+            shared_bridge_code_size: self.shared_bridge_code.size,
             ctm_deployer: format_address(self.ctm_deployer),
+            owner: self.owner.map(format_address),
+            admin: self.admin.map(format_address),
+            shared_bridge_owner: self.shared_bridge_owner.map(format_address),
             known_chains,
+            ctm_unknown_chains: self.ctm_unknown_chains.clone(),
             ctms,
             asset_router,
+            asset_registrations: vec![],
+            ctm_asset_map: self
+                .ctm_asset_map()
+                .iter()
+                .map(CtmAssetMappingSummary::from)
+                .collect(),
+            consistency_warnings: self.consistency_warnings(),
         }
     }
 
-    pub async fn new(sequencer: &Sequencer, address: Address) -> eyre::Result<Bridgehub> {
+    pub fn ctm_asset_map(&self) -> Vec<CtmAssetMapping> {
+        build_ctm_asset_map(self.ctms.as_deref().unwrap_or_default(), &self.ctm_chains)
+    }
+
+    pub fn sorted_known_chains(&self) -> Vec<u64> {
+        let mut known_chains: Vec<u64> = self.known_chains.iter().copied().collect();
+        known_chains.sort_unstable();
+        known_chains
+    }
+
+    // Checks that the shared bridge and the CTMs point back to this bridgehub.
+    pub fn consistency_warnings(&self) -> Vec<String> {
+        let shared_bridge_bridgehub = match &self.asset_router {
+            AssetRouter::L1(router) => router.bridgehub,
+            AssetRouter::L2(_) => None,
+        };
+        let ctms: Vec<(Address, Address)> = self
+            .ctms
+            .iter()
+            .flatten()
+            .map(|ctm| (ctm.address, ctm.bridgehub))
+            .collect();
+        wiring_warnings(
+            self.address,
+            self.shared_bridge,
+            shared_bridge_bridgehub,
+            &ctms,
+        )
+    }
+
+    // Decodes the AssetRegistered events (from the recent blocks), and checks each of them
+    // against the current ctmAssetIdFromAddress mapping.
+    pub async fn registered_assets_from_events(
+        &self,
+        sequencer: &Sequencer,
+    ) -> eyre::Result<Vec<AssetRegistration>> {
+        let events = get_all_events(
+            sequencer,
+            self.address,
+            IBridgehub::AssetRegistered::SIGNATURE_HASH,
+            5000, // 5k block limit
+        )
+        .await?;
+
+        let contract = IBridgehub::new(self.address, &self.provider);
+        let mut result = vec![];
+        for log in events {
+            let block = log.block_number;
+            let event = log.log_decode::<IBridgehub::AssetRegistered>()?.inner.data;
+            let matches_current_state = contract
+                .ctmAssetIdFromAddress(event._assetAddress)
+                .call()
+                .await
+                .ok()
+                .map(|current| current._0 == event.assetInfo);
+
+            result.push(AssetRegistration {
+                asset_info: event.assetInfo,
+                asset_address: event._assetAddress,
+                additional_data: event.additionalData,
+                sender: event.sender,
+                block,
+                matches_current_state,
+            });
+        }
+        result.sort_by_key(|registration| registration.block);
+        Ok(result)
+    }
+
+    pub async fn new_with_options(
+        sequencer: &Sequencer,
+        address: Address,
+        options: BridgehubOptions,
+    ) -> eyre::Result<Bridgehub> {
         let provider = sequencer.get_provider();
 
         let data = provider.get_code_at(address).await?;
-        if data.len() == 0 {
+        if data.is_empty() {
             // empty contract - something's wrong.
-            eyre::bail!(
-                "Trying to read bridgehub data from address {} at {}, but code is empty. Is it a rigth address on right chain?",
+            return Err(DebuggerError::EmptyContractCode {
                 address,
-                provider.client().transport().url()
-            );
+                rpc_url: provider.client().transport().url().to_string(),
+            }
+            .into());
         }
 
+        let code = ContractCode::from_bytes(&data);
+        let implementation = get_proxy_implementation(&provider, address).await?;
+
         let contract = IBridgehub::new(address, provider);
         let shared_bridge = contract.sharedBridge().call().await?.sharedBridge;
+        let shared_bridge_implementation =
+            get_proxy_implementation(contract.provider(), shared_bridge).await?;
+        let shared_bridge_code = ContractCode::fetch(contract.provider(), shared_bridge).await?;
 
         let known_chains = contract.getAllZKChainChainIDs().call().await?._0;
 
-        let known_chains: HashSet<u64> =
-            known_chains.iter().map(|x| x.try_into().unwrap()).collect();
+        // Chain ids are U256 on-chain - skip anything that doesn't fit into u64 rather than panic.
+        let known_chains: HashSet<u64> = known_chains
+            .iter()
+            .filter_map(|chain_id| match u64::try_from(*chain_id) {
+                Ok(chain_id) => Some(chain_id),
+                Err(_) => {
+                    progress!(
+                        "{} Skipping chain id {} on bridgehub {} - doesn't fit into u64",
+                        "[WARN]".yellow(),
+                        chain_id,
+                        address
+                    );
+                    None
+                }
+            })
+            .collect();
 
         let ctm_deployer = contract.l1CtmDeployer().call().await?.l1CtmDeployer;
 
-        let mut ctm_addresses = HashSet::new();
+        let owner = contract.owner().call().await.ok().map(|owner| owner._0);
+        let admin = contract.admin().call().await.ok().map(|admin| admin._0);
+        let shared_bridge_owner = IOwnable::new(shared_bridge, contract.provider())
+            .owner()
+            .call()
+            .await
+            .ok()
+            .map(|owner| owner._0);
+        let layer = match sequencer.sequencer_type {
+            crate::sequencer::SequencerType::L1 => "L1".to_string(),
+            crate::sequencer::SequencerType::L2(_) => format!("chain {}", sequencer.chain_id),
+        };
+        for (address, role) in [
+            (owner, "Bridgehub owner"),
+            (admin, "Bridgehub admin"),
+            (shared_bridge_owner, "Shared bridge owner"),
+        ] {
+            if let Some(address) = address.filter(|address| *address != Address::ZERO) {
+                add_address_name(address, format!("{} ({})", role, layer));
+            }
+        }
+
+        let mut ctm_chains: HashMap<Address, Vec<u64>> = HashMap::new();
+        let mut ctm_unknown_chains = vec![];
 
-        for chain_id in known_chains.iter() {
-            let aa = contract
+        let mut sorted_chains: Vec<u64> = known_chains.iter().copied().collect();
+        sorted_chains.sort_unstable();
+        for chain_id in sorted_chains.iter().filter(|_| options.resolve_ctms) {
+            match contract
                 .chainTypeManager(U256::from(*chain_id))
                 .call()
                 .await
-                .map(|x| x._0)
-                .unwrap();
-            ctm_addresses.insert(aa);
+            {
+                Ok(ctm) => ctm_chains.entry(ctm._0).or_default().push(*chain_id),
+                Err(err) => {
+                    progress!(
+                        "{} Failed to fetch CTM for chain {}: {}",
+                        "[WARN]".yellow(),
+                        chain_to_human(*chain_id),
+                        err
+                    );
+                    ctm_unknown_chains.push(*chain_id);
+                }
+            }
         }
-
-        let ctms = {
-            let stms = ctm_addresses
-                .into_iter()
-                .map(|address| ChainTypeManager::new(sequencer, address));
-            let stms = join_all(stms).await;
+        ctm_unknown_chains.sort_unstable();
+
+        let ctms = if options.resolve_ctms {
+            let mut stms: Vec<ChainTypeManager> = stream::iter(ctm_chains.keys().copied())
+                .map(|address| ChainTypeManager::new(sequencer, address))
+                .buffer_unordered(options.concurrency.max(1))
+                .collect()
+                .await;
+            stms.sort_by_key(|stm| stm.address);
             Some(stms)
+        } else {
+            None
         };
 
         let asset_router = match sequencer.sequencer_type {
@@ -328,25 +796,85 @@ impl Bridgehub {
 
         Ok(Bridgehub {
             address,
+            implementation,
+            code,
             shared_bridge,
+            shared_bridge_implementation,
+            shared_bridge_code,
             known_chains,
+            ctm_unknown_chains,
             provider: sequencer.get_provider(),
             ctms,
+            ctm_chains,
             ctm_deployer,
+            owner,
+            admin,
+            shared_bridge_owner,
             asset_router,
         })
     }
 
-    pub async fn print_detailed_info(&self) -> eyre::Result<()> {
-        println!("  Bridgehub:          {}", self.address);
+    // Prints the details of every known chain, and returns them for further use.
+    pub async fn print_detailed_info(&self) -> eyre::Result<HashMap<u64, BridgehubChainDetails>> {
+        progress!("  Bridgehub:          {}", self.address);
 
-        for chain_id in &self.known_chains {
-            println!("{}", format!("  Chain: {:?}", chain_id).bold());
-            let details = self.get_chain_details(*chain_id).await?;
-            println!("{}", details);
+        let mut result = HashMap::new();
+        for chain_id in &self.sorted_known_chains() {
+            progress!(
+                "{}",
+                format!("  Chain: {}", chain_to_human(*chain_id)).bold()
+            );
+            match self.get_chain_details(*chain_id).await {
+                Ok(details) => {
+                    progress!("{}", details);
+                    result.insert(*chain_id, details);
+                }
+                Err(err) => progress!(
+                    "    {} Failed to fetch chain details: {}",
+                    "[WARN]".yellow(),
+                    err
+                ),
+            }
         }
 
-        Ok(())
+        Ok(result)
+    }
+
+    // Finds the bridgehub on the given L2, if that L2 acts as a settlement layer (gateway)
+    // for this (L1) bridgehub. Returns None if there is no gateway there.
+    pub async fn detect_gateway_bridgehub(
+        &self,
+        l2_sequencer: &Sequencer,
+    ) -> eyre::Result<Option<Address>> {
+        let contract = IBridgehub::new(self.address, &self.provider);
+        // Older bridgehubs don't have the settlement layers whitelist - in that case
+        // rely on the code check only.
+        if let Ok(whitelisted) = contract
+            .whitelistedSettlementLayers(U256::from(l2_sequencer.chain_id))
+            .call()
+            .await
+        {
+            if !whitelisted._0 {
+                return Ok(None);
+            }
+        }
+
+        let l2_bridgehub = system_addresses().l2_bridgehub;
+        let code = l2_sequencer
+            .get_provider()
+            .get_code_at(l2_bridgehub)
+            .await?;
+        Ok((!code.is_empty()).then_some(l2_bridgehub))
+    }
+
+    // Chain id of the settlement layer that this bridgehub believes the chain settles on.
+    pub async fn get_settlement_layer(&self, chain_id: u64) -> eyre::Result<U256> {
+        let contract = IBridgehub::new(self.address, &self.provider);
+        Ok(contract
+            .settlementLayer(U256::from(chain_id))
+            .call()
+            .await?
+            ._0)
     }
 
     pub async fn get_chain_details(&self, chain_id: u64) -> eyre::Result<BridgehubChainDetails> {
@@ -365,10 +893,19 @@ impl Bridgehub {
             .await?
             ._0;
 
-        let base_token_address = match contract.baseToken(U256::from(chain_id)).call().await {
-            Ok(base_token) => base_token._0,
-            // FIXME: remove after we fix an issue where basetoken is not set after migration.
-            Err(_) => Address::ZERO,
+        // The query reverts on some migrated chains, where the base token isn't set after the
+        // migration - that's reported as unavailable rather than failing the whole chain.
+        let base_token_address = contract
+            .baseToken(U256::from(chain_id))
+            .call()
+            .await
+            .ok()
+            .map(|base_token| base_token._0);
+        let base_token = match base_token_address {
+            Some(address) if address != Address::ZERO => {
+                resolve_token_metadata(&self.provider, address).await
+            }
+            _ => None,
         };
         let st_address = contract
             .getHyperchain(U256::from(chain_id))
@@ -394,63 +931,294 @@ impl Bridgehub {
             stm_address,
             st_address,
             base_token_address,
+            base_token,
             validator_timelock_address,
             stm_asset_id: asset_id,
         })
     }
 
-    pub async fn get_state_transition(&self, chain_id: u64) -> eyre::Result<StateTransition> {
+    pub async fn get_hyperchain(&self, chain_id: u64) -> eyre::Result<Address> {
         let contract = IBridgehub::new(self.address, &self.provider);
 
-        let st_address = contract
+        Ok(contract
             .getHyperchain(U256::from(chain_id))
             .call()
             .await?
-            ._0;
+            ._0)
+    }
+
+    pub async fn get_state_transition(&self, chain_id: u64) -> eyre::Result<StateTransition> {
+        let st_address = self.get_hyperchain(chain_id).await?;
+        self.load_state_transition(st_address).await
+    }
+
+    pub async fn load_state_transition(
+        &self,
+        st_address: Address,
+    ) -> eyre::Result<StateTransition> {
         StateTransition::new(&self.provider, st_address).await
     }
 
-    pub async fn get_all_chains_balances(
+    // Finds the layer that the chain currently settles on (`self` being the L1 bridgehub).
+    // A chain that is registered on L1, but whose hyperchain points to a different settlement
+    // layer, is looked up on the gateway.
+    pub async fn locate_chain(
         &self,
-        sequencer: &Sequencer,
-    ) -> eyre::Result<HashMap<u64, HashMap<String, U256>>> {
-        let mut result = HashMap::new();
+        gateway: Option<&Bridgehub>,
+        chain_id: u64,
+    ) -> eyre::Result<ChainLocation> {
+        if self.known_chains.contains(&chain_id) {
+            let hyperchain = self.get_hyperchain(chain_id).await?;
+            if get_settlement_layer(&self.provider, hyperchain).await? == Address::ZERO {
+                return Ok(ChainLocation::L1 {
+                    state_transition: hyperchain,
+                });
+            }
+        }
 
-        for chain_id in &self.known_chains {
-            let foo = self.get_chain_balances(sequencer, *chain_id).await?;
-            result.insert(*chain_id, foo);
+        if let Some(gateway) = gateway {
+            if gateway.known_chains.contains(&chain_id) {
+                return Ok(ChainLocation::Gateway {
+                    state_transition: gateway.get_hyperchain(chain_id).await?,
+                });
+            }
         }
 
-        Ok(result)
+        Ok(ChainLocation::Unknown)
+    }
+
+    // Symbols of the tokens, keyed like the balances (by asset name).
+    pub fn token_symbols(&self) -> HashMap<String, String> {
+        match &self.asset_router {
+            AssetRouter::L1(router) => router
+                .registered_assets
+                .values()
+                .filter_map(|asset| Some((asset.name(), asset.symbol()?.to_string())))
+                .collect(),
+            AssetRouter::L2(_) => HashMap::new(),
+        }
+    }
+
+    // ERC20 decimals by token (asset name), for the tokens that expose them.
+    pub fn token_decimals(&self) -> HashMap<String, u8> {
+        match &self.asset_router {
+            AssetRouter::L1(router) => router
+                .registered_assets
+                .values()
+                .filter_map(|asset| Some((asset.name(), asset.decimals()?)))
+                .collect(),
+            AssetRouter::L2(_) => HashMap::new(),
+        }
+    }
+
+    // Fetches balances of all the chains, running up to `concurrency` requests at once.
+    pub async fn get_all_chains_balances(
+        &self,
+        sequencer: &Sequencer,
+        concurrency: usize,
+    ) -> eyre::Result<HashMap<u64, HashMap<String, U256>>> {
+        self.get_balances(sequencer, &self.sorted_known_chains(), concurrency)
+            .await
     }
 
     pub async fn get_chain_balances(
         &self,
         sequencer: &Sequencer,
         chain_id: u64,
+        concurrency: usize,
     ) -> eyre::Result<HashMap<String, U256>> {
-        let mut result = HashMap::new();
+        let mut balances = self
+            .get_balances(sequencer, &[chain_id], concurrency)
+            .await?;
+        Ok(balances.remove(&chain_id).unwrap_or_default())
+    }
+
+    // Balances of the given chains. All the (chain, token) pairs share one stream, so at most
+    // `concurrency` requests are in flight however many chains there are.
+    async fn get_balances(
+        &self,
+        sequencer: &Sequencer,
+        chains: &[u64],
+        concurrency: usize,
+    ) -> eyre::Result<HashMap<u64, HashMap<String, U256>>> {
         match &self.asset_router {
             AssetRouter::L1(router) => {
-                let assets = router.registered_assets.iter().filter_map(|(k, x)| {
-                    if let AssetHandler::NativeTokenVault(_) = &x.handler {
-                        Some((k, x))
-                    } else {
-                        None
-                    }
-                });
-                for (asset_id, asset) in assets {
-                    let amount = router
-                        .chain_balance(sequencer, chain_id.try_into().unwrap(), asset_id)
-                        .await;
-
-                    result.insert(asset.name(), amount);
+                let assets: Vec<_> = router
+                    .sorted_assets()
+                    .into_iter()
+                    .filter(|x| matches!(x.handler, AssetHandler::NativeTokenVault(_)))
+                    .collect();
+                let pairs = chains
+                    .iter()
+                    .flat_map(|chain_id| assets.iter().map(move |asset| (*chain_id, *asset)));
+                let amounts: Vec<_> = stream::iter(pairs)
+                    .map(|(chain_id, asset)| async move {
+                        let amount = router
+                            .chain_balance(sequencer, U256::from(chain_id), &asset.asset_id)
+                            .await;
+                        (chain_id, asset.name(), amount)
+                    })
+                    .buffer_unordered(concurrency.max(1))
+                    .collect()
+                    .await;
+
+                let mut balances: HashMap<u64, HashMap<String, U256>> = chains
+                    .iter()
+                    .map(|chain_id| (*chain_id, HashMap::new()))
+                    .collect();
+                for (chain_id, token, amount) in amounts {
+                    balances.entry(chain_id).or_default().insert(token, amount);
                 }
+                Ok(balances)
             }
 
             AssetRouter::L2(_) => eyre::bail!("Not implemented yet"),
-        };
+        }
+    }
+}
 
-        Ok(result)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statetransition::IHyperchain;
+    use crate::test_utils::MockTransport;
+    use alloy::primitives::address;
+    use alloy::sol_types::SolCall;
+
+    const BRIDGEHUB: Address = address!("1000000000000000000000000000000000000001");
+    const SHARED_BRIDGE: Address = address!("2000000000000000000000000000000000000002");
+    const CTM: Address = address!("3000000000000000000000000000000000000003");
+    const STALE: Address = address!("4000000000000000000000000000000000000004");
+
+    #[test]
+    fn consistent_wiring_has_no_warnings() {
+        let warnings = wiring_warnings(
+            BRIDGEHUB,
+            SHARED_BRIDGE,
+            Some(BRIDGEHUB),
+            &[(CTM, BRIDGEHUB)],
+        );
+        assert!(warnings.is_empty());
+        assert!(wiring_warnings(BRIDGEHUB, SHARED_BRIDGE, None, &[]).is_empty());
+    }
+
+    fn ctm(address: Address, name: &str) -> ChainTypeManager {
+        ChainTypeManager {
+            address,
+            implementation: None,
+            bridgehub: BRIDGEHUB,
+            admin: Address::ZERO,
+            owner: Address::ZERO,
+            asset_id: FixedBytes::repeat_byte(address.0[0]),
+            asset_name: name.to_string(),
+            migrations: vec![],
+        }
+    }
+
+    #[test]
+    fn maps_ctm_asset_ids_to_chains() {
+        let ctms = [ctm(STALE, "silent_river"), ctm(CTM, "brave_otter")];
+        let ctm_chains = HashMap::from([(CTM, vec![388, 324])]);
+
+        let map = build_ctm_asset_map(&ctms, &ctm_chains);
+
+        assert_eq!(
+            map,
+            vec![
+                CtmAssetMapping {
+                    ctm_address: CTM,
+                    asset_id: FixedBytes::repeat_byte(0x30),
+                    human_name: "brave_otter".to_string(),
+                    chain_ids: vec![324, 388],
+                },
+                CtmAssetMapping {
+                    ctm_address: STALE,
+                    asset_id: FixedBytes::repeat_byte(0x40),
+                    human_name: "silent_river".to_string(),
+                    chain_ids: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_stale_bridgehubs() {
+        let warnings = wiring_warnings(BRIDGEHUB, SHARED_BRIDGE, Some(STALE), &[(CTM, STALE)]);
+        assert_eq!(
+            warnings,
+            vec![
+                format!(
+                    "Shared bridge {} points to bridgehub {}, not {}",
+                    SHARED_BRIDGE, STALE, BRIDGEHUB
+                ),
+                format!(
+                    "CTM {} points to bridgehub {}, not {}",
+                    CTM, STALE, BRIDGEHUB
+                ),
+            ]
+        );
+    }
+
+    const HYPERCHAIN: Address = address!("5000000000000000000000000000000000000005");
+
+    fn bridgehub_mock(mock: MockTransport, chains: &[u64]) -> Bridgehub {
+        let code = ContractCode::from_bytes(&[0x60]);
+        Bridgehub {
+            address: BRIDGEHUB,
+            implementation: None,
+            code,
+            shared_bridge: SHARED_BRIDGE,
+            shared_bridge_implementation: None,
+            shared_bridge_code: code,
+            known_chains: chains.iter().copied().collect(),
+            ctm_unknown_chains: vec![],
+            ctms: None,
+            ctm_chains: HashMap::new(),
+            provider: mock
+                .with_call::<IBridgehub::getHyperchainCall>(
+                    IBridgehub::getHyperchainCall::abi_encode_returns(&(HYPERCHAIN,)),
+                )
+                .provider(),
+            ctm_deployer: Address::ZERO,
+            owner: None,
+            admin: None,
+            shared_bridge_owner: None,
+            asset_router: AssetRouter::L2(L2AssetRouter {
+                address: Address::ZERO,
+                l1_router: None,
+            }),
+        }
+    }
+
+    fn hyperchain_version(minor: u32) -> MockTransport {
+        MockTransport::default().with_call::<IHyperchain::getSemverProtocolVersionCall>(
+            IHyperchain::getSemverProtocolVersionCall::abi_encode_returns(&(0u32, minor, 0u32)),
+        )
+    }
+
+    #[tokio::test]
+    async fn pre_gateway_chains_are_located_on_l1() {
+        // getSettlementLayer reverts on v25 - it must not be called.
+        let bridgehub = bridgehub_mock(hyperchain_version(25), &[500]);
+
+        assert_eq!(
+            bridgehub.locate_chain(None, 500).await.unwrap(),
+            ChainLocation::L1 {
+                state_transition: HYPERCHAIN
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn chains_settling_elsewhere_are_not_located_on_l1() {
+        let mock = hyperchain_version(26).with_call::<IHyperchain::getSettlementLayerCall>(
+            IHyperchain::getSettlementLayerCall::abi_encode_returns(&(STALE,)),
+        );
+        let bridgehub = bridgehub_mock(mock, &[500]);
+
+        assert_eq!(
+            bridgehub.locate_chain(None, 500).await.unwrap(),
+            ChainLocation::Unknown
+        );
     }
 }
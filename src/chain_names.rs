@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+// Human readable labels for chain ids, similar to the address mapping in addresses.rs.
+lazy_static! {
+    static ref CHAIN_NAMES: RwLock<HashMap<u64, String>> = {
+        let initial_entries = vec![
+            (232, "Lens"),
+            (324, "Era"),
+            (388, "Cronos zkEVM"),
+            (2741, "Abstract"),
+            (9075, "Gateway"),
+            (50104, "Sophon"),
+            (61166, "Treasure"),
+            (543210, "ZERO Network"),
+        ];
+        let mut m = HashMap::new();
+        for entry in initial_entries {
+            m.insert(entry.0, entry.1.to_string());
+        }
+
+        RwLock::new(m)
+    };
+}
+
+pub fn add_chain_name(chain_id: u64, name: String) {
+    let mut map = CHAIN_NAMES.write().unwrap();
+    map.insert(chain_id, name);
+}
+
+// Loads a JSON object of `{"<chain_id>": "<name>"}` entries, overriding the built-in names.
+pub fn load_chain_names(path: &Path) -> eyre::Result<()> {
+    let entries: HashMap<String, String> = serde_json::from_slice(&fs::read(path)?)?;
+    for (chain_id, name) in entries {
+        let chain_id = chain_id
+            .parse()
+            .map_err(|_| eyre::eyre!("Invalid chain id '{}' in {}", chain_id, path.display()))?;
+        add_chain_name(chain_id, name);
+    }
+    Ok(())
+}
+
+pub fn chain_name(chain_id: u64) -> Option<String> {
+    let map = CHAIN_NAMES.read().unwrap();
+    map.get(&chain_id).cloned()
+}
+
+pub fn chain_to_human(chain_id: u64) -> String {
+    match chain_name(chain_id) {
+        Some(name) => format!("{} ({})", chain_id, name),
+        None => chain_id.to_string(),
+    }
+}
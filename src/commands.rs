@@ -0,0 +1,533 @@
+// Standalone subcommands - quick, focused alternatives to the full diagnostics run.
+
+use std::fs;
+use std::path::Path;
+
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::Provider;
+use colored::Colorize;
+
+use crate::addresses::u256_to_address;
+use crate::bridgehub::{Bridgehub, BridgehubOptions};
+use crate::chain_names::chain_to_human;
+use crate::error::DebuggerError;
+use crate::priority_transactions::{decode_priority_log_json, find_by_tx_id, PriorityScanRange};
+use crate::sequencer::{detect_l2_sequencer, detect_sequencer, Sequencer, SequencerType};
+use crate::statetransition::StateTransition;
+use crate::{
+    gateway_bridgehub_address, layer_name, load_located_state_transition, select_bridgehub_address,
+    Cli, Layer, TokenBalanceReport,
+};
+
+// Prints the chain ids registered on the L1 bridgehub (and on the gateway one, if present).
+pub async fn list_chains(args: &Cli, json: bool) -> eyre::Result<()> {
+    let (l1_rpc, l2_rpc, l3_rpc) = args.rpc_urls();
+
+    let l1_sequencer = detect_sequencer(l1_rpc).await?;
+    let l2_sequencer = detect_l2_sequencer(l2_rpc).await;
+    let l3_sequencer = detect_l2_sequencer(l3_rpc).await;
+
+    let bridgehub_address = match args.bridgehub {
+        Some(address) => address,
+        None => select_bridgehub_address(&l2_sequencer, &l3_sequencer)?,
+    };
+    // Only the chain ids are needed - don't spend calls on the CTMs.
+    let options = BridgehubOptions {
+        resolve_ctms: false,
+        concurrency: args.concurrency,
+    };
+    let bridgehub = Bridgehub::new_with_options(&l1_sequencer, bridgehub_address, options).await?;
+    let l1_chains = bridgehub.sorted_known_chains();
+
+    let gateway = match &l2_sequencer {
+        Ok(l2_sequencer) => gateway_bridgehub_address(args, &bridgehub, l2_sequencer)
+            .await
+            .map(|address| (l2_sequencer, address)),
+        Err(_) => None,
+    };
+    let gateway_chains = match gateway {
+        Some((l2_sequencer, gateway_bridgehub_address)) => {
+            let gateway_bridgehub =
+                Bridgehub::new_with_options(l2_sequencer, gateway_bridgehub_address, options)
+                    .await?;
+            Some(gateway_bridgehub.sorted_known_chains())
+        }
+        None => None,
+    };
+
+    if json {
+        let output = serde_json::json!({
+            "l1": l1_chains,
+            "gateway": gateway_chains,
+        });
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        println!("L1 bridgehub {}:", bridgehub_address);
+        for chain in &l1_chains {
+            println!("  {}", chain_to_human(*chain));
+        }
+        if let Some(gateway_chains) = &gateway_chains {
+            println!("Gateway bridgehub:");
+            for chain in gateway_chains {
+                println!("  {}", chain_to_human(*chain));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Reads a raw storage slot - an escape hatch for state that the typed getters don't expose.
+pub async fn read_slot(args: &Cli, address: Address, slot: U256, layer: Layer) -> eyre::Result<()> {
+    let (l1_rpc, l2_rpc, l3_rpc) = args.rpc_urls();
+    let sequencer = match layer {
+        Layer::L1 => detect_sequencer(l1_rpc).await?,
+        Layer::L2 => detect_l2_sequencer(l2_rpc).await?,
+        Layer::L3 => detect_l2_sequencer(l3_rpc).await?,
+    };
+
+    let value = sequencer
+        .get_provider()
+        .get_storage_at(address, slot)
+        .await?;
+
+    println!(
+        "Slot {:#x} of {} on chain {}:",
+        slot,
+        address,
+        chain_to_human(sequencer.chain_id)
+    );
+    for (label, interpretation) in slot_interpretations(value) {
+        println!("  {:<8} {}", label, interpretation);
+    }
+    Ok(())
+}
+
+// Prints the proof of an L2 -> L1 log - handy when debugging withdrawal finalization.
+pub async fn proof_log(
+    args: &Cli,
+    tx_hash: B256,
+    index: Option<u64>,
+    layer: Layer,
+) -> eyre::Result<()> {
+    let (_, l2_rpc, l3_rpc) = args.rpc_urls();
+    let sequencer = match layer {
+        Layer::L1 => eyre::bail!("L2 -> L1 log proofs are served by the L2 / L3 sequencers"),
+        Layer::L2 => detect_l2_sequencer(l2_rpc).await?,
+        Layer::L3 => detect_l2_sequencer(l3_rpc).await?,
+    };
+
+    match sequencer.get_l2_to_l1_log_proof(tx_hash, index).await? {
+        Some(proof) => {
+            println!(
+                "L2 -> L1 log {} of {} on chain {}:",
+                index.unwrap_or_default(),
+                tx_hash,
+                chain_to_human(sequencer.chain_id)
+            );
+            println!("  id:    {}", proof.id);
+            println!("  root:  {}", proof.root);
+            println!("  proof:");
+            for hash in &proof.proof {
+                println!("    {}", hash);
+            }
+        }
+        None => println!(
+            "No proof for log {} of {} (no such log, or its batch isn't sealed yet)",
+            index.unwrap_or_default(),
+            tx_hash
+        ),
+    }
+    Ok(())
+}
+
+// Decodes a NewPriorityRequest log from a file, the same way the diagnostics run does.
+pub fn decode_priority(log_file: &Path) -> eyre::Result<()> {
+    let tx = decode_priority_log_json(&fs::read(log_file)?)?;
+    let report = tx.to_report();
+
+    print!("{}", tx);
+    println!("    From             - {}", report.from);
+    println!("    To               - {}", report.to);
+    println!(
+        "    Method           - {}",
+        report.method.as_deref().unwrap_or("-")
+    );
+    println!("    Gas limit        - {}", report.gas_limit);
+    println!(
+        "    Gas per pubdata  - {}",
+        report.gas_per_pubdata_byte_limit
+    );
+    println!("    Max fee per gas  - {}", report.max_fee_per_gas);
+    println!("    Value            - {}", report.value_formatted);
+    Ok(())
+}
+
+// The L1 bridgehub and the gateway one (if there is a gateway), without their CTMs - enough
+// to locate the chains and load their state transitions.
+struct Bridgehubs {
+    l1_sequencer: Sequencer,
+    bridgehub: Bridgehub,
+    gateway: Option<(Bridgehub, Sequencer)>,
+}
+
+impl Bridgehubs {
+    async fn load(args: &Cli) -> eyre::Result<Self> {
+        let (l1_rpc, l2_rpc, l3_rpc) = args.rpc_urls();
+
+        let l1_sequencer = detect_sequencer(l1_rpc).await?;
+        let l2_sequencer = detect_l2_sequencer(l2_rpc).await;
+        let l3_sequencer = detect_l2_sequencer(l3_rpc).await;
+
+        let bridgehub_address = match args.bridgehub {
+            Some(address) => address,
+            None => select_bridgehub_address(&l2_sequencer, &l3_sequencer)?,
+        };
+        let options = BridgehubOptions {
+            resolve_ctms: false,
+            concurrency: args.concurrency,
+        };
+        let bridgehub =
+            Bridgehub::new_with_options(&l1_sequencer, bridgehub_address, options).await?;
+        let gateway = match l2_sequencer {
+            Ok(l2_sequencer) => {
+                match gateway_bridgehub_address(args, &bridgehub, &l2_sequencer).await {
+                    Some(address) => Some((
+                        Bridgehub::new_with_options(&l2_sequencer, address, options).await?,
+                        l2_sequencer,
+                    )),
+                    None => None,
+                }
+            }
+            Err(_) => None,
+        };
+        Ok(Self {
+            l1_sequencer,
+            bridgehub,
+            gateway,
+        })
+    }
+
+    fn gateway(&self) -> Option<(&Bridgehub, &Sequencer)> {
+        self.gateway
+            .as_ref()
+            .map(|(bridgehub, sequencer)| (bridgehub, sequencer))
+    }
+
+    // Chains of both bridgehubs, in order.
+    fn sorted_chains(&self) -> Vec<u64> {
+        let mut chains = self.bridgehub.sorted_known_chains();
+        if let Some((gateway_bridgehub, _)) = self.gateway() {
+            chains.extend(gateway_bridgehub.sorted_known_chains());
+        }
+        chains.sort_unstable();
+        chains.dedup();
+        chains
+    }
+
+    // State transition of the chain, from the layer it settles on.
+    async fn state_transition(&self, chain: u64) -> eyre::Result<(StateTransition, &Sequencer)> {
+        let location = self
+            .bridgehub
+            .locate_chain(self.gateway().map(|(bridgehub, _)| bridgehub), chain)
+            .await?;
+        load_located_state_transition(
+            chain,
+            location,
+            &self.bridgehub,
+            &self.l1_sequencer,
+            self.gateway(),
+        )
+        .await
+    }
+}
+
+// Prints the stored hash, commitment and status of a single batch of the chain.
+pub async fn batch_info(args: &Cli, chain: u64, batch: u64) -> eyre::Result<()> {
+    let bridgehubs = Bridgehubs::load(args).await?;
+    let (st, sequencer) = bridgehubs.state_transition(chain).await?;
+    let info = st.batch_info(sequencer, batch).await?;
+
+    println!(
+        "Batch {} of chain {} (on {}):",
+        info.number,
+        chain_to_human(chain),
+        layer_name(sequencer)
+    );
+    println!("  Status:      {}", info.status);
+    println!("  Stored hash: {}", info.stored_hash);
+    match info.commitment {
+        Some(commitment) => println!("  Commitment:  {}", commitment),
+        None => println!("  Commitment:  not found in the recent BlockCommit events"),
+    }
+    Ok(())
+}
+
+// Looks the transaction up among the chain's priority transactions (a user reporting a stuck
+// L1 -> L2 transaction usually only has its hash), and prints where it is in the queue. Only
+// chains settling on L1 are supported - the gateway's priority transactions aren't scanned.
+pub async fn find_priority(args: &Cli, chain: u64, tx_hash: B256) -> eyre::Result<()> {
+    let bridgehubs = Bridgehubs::load(args).await?;
+    let (st, sequencer) = bridgehubs.state_transition(chain).await?;
+    if let SequencerType::L2(_) = sequencer.sequencer_type {
+        eyre::bail!(
+            "Chain {} settles on the gateway - find-priority only looks up priority transactions of chains settling on L1",
+            chain_to_human(chain)
+        );
+    }
+    let txs = st
+        .get_priority_transactions(sequencer, PriorityScanRange::Recent)
+        .await?
+        .txs;
+
+    let Some(tx) = find_by_tx_id(&txs, tx_hash) else {
+        let indices = txs.iter().map(|tx| tx.index);
+        let scanned = match (indices.clone().min(), indices.max()) {
+            (Some(min), Some(max)) => format!("indices {} - {}", min, max),
+            _ => "no transactions".to_string(),
+        };
+        println!(
+            "Priority transaction {} of chain {}: {} ({} scanned, {})",
+            tx_hash,
+            chain_to_human(chain),
+            "not found in scanned range".yellow(),
+            txs.len(),
+            scanned
+        );
+        return Ok(());
+    };
+
+    let status = if st.is_priority_processed(tx.index) {
+        "processed".green()
+    } else {
+        "pending".yellow()
+    };
+    println!(
+        "Priority transaction {} of chain {} (on {}):",
+        tx.tx_id(),
+        chain_to_human(chain),
+        layer_name(sequencer)
+    );
+    println!("  Index:      {}", tx.index);
+    println!("  Expiration: {}", tx.expiration_timestamp());
+    println!(
+        "  Status:     {} ({} transactions processed so far)",
+        status,
+        st.processed_queue_size()
+    );
+    print!("{}", tx);
+    Ok(())
+}
+
+// Recomputes the priority tree of every chain (or just of `chain`) and compares it with the
+// on-chain root. Fails if any tree doesn't match or couldn't be verified - chains that predate
+// the priority tree are only skipped.
+pub async fn verify_priority_tree(args: &Cli, chain: Option<u64>) -> eyre::Result<()> {
+    let bridgehubs = Bridgehubs::load(args).await?;
+    let chains = match chain {
+        Some(chain) => vec![chain],
+        None => bridgehubs.sorted_chains(),
+    };
+
+    let mut failed = Vec::new();
+    for chain in chains {
+        let verification = match bridgehubs.state_transition(chain).await {
+            Ok((st, sequencer)) => st.verify_priority_root_hash(sequencer).await,
+            Err(err) => Err(err),
+        };
+        match verification {
+            Ok(verification) if verification.is_valid() => {
+                println!("Chain {}: {}", chain_to_human(chain), "VALID".green());
+            }
+            Ok(verification) => {
+                println!(
+                    "Chain {}: {} (on-chain {} vs computed {})",
+                    chain_to_human(chain),
+                    "INVALID".red(),
+                    verification.onchain_root,
+                    verification.computed_root
+                );
+                failed.push(chain);
+            }
+            Err(err)
+                if matches!(
+                    err.downcast_ref::<DebuggerError>(),
+                    Some(
+                        DebuggerError::NoPriorityTree { .. } | DebuggerError::PriorityTxOnL2 { .. }
+                    )
+                ) =>
+            {
+                println!(
+                    "Chain {}: {} - {}",
+                    chain_to_human(chain),
+                    "SKIPPED".yellow(),
+                    err
+                );
+            }
+            Err(err) => {
+                println!(
+                    "Chain {}: {} - {}",
+                    chain_to_human(chain),
+                    "UNVERIFIED".red(),
+                    err
+                );
+                failed.push(chain);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        eyre::bail!("Priority tree verification failed for chains {:?}", failed);
+    }
+    println!("{}", "Priority trees: PASS".green());
+    Ok(())
+}
+
+// What chain_balances needs, so that it can be called without the command line: the (comma
+// separated) RPC urls of each layer, the bridgehub (detected from the L2s if not set) and the
+// number of parallel requests.
+pub struct BalancesConfig {
+    pub l1_rpc: String,
+    pub l2_rpc: String,
+    pub l3_rpc: String,
+    pub bridgehub: Option<Address>,
+    pub concurrency: usize,
+}
+
+impl BalancesConfig {
+    fn from_cli(args: &Cli) -> Self {
+        let (l1_rpc, l2_rpc, l3_rpc) = args.rpc_urls();
+        Self {
+            l1_rpc: l1_rpc.to_string(),
+            l2_rpc: l2_rpc.to_string(),
+            l3_rpc: l3_rpc.to_string(),
+            bridgehub: args.bridgehub,
+            concurrency: args.concurrency,
+        }
+    }
+}
+
+// Bridge balances of a single chain, held by the L1 asset router. Only the bridgehub and its
+// asset router are loaded - none of the state transition / priority transaction work.
+pub async fn chain_balances(
+    config: &BalancesConfig,
+    chain_id: u64,
+) -> eyre::Result<Vec<TokenBalanceReport>> {
+    let l1_sequencer = detect_sequencer(&config.l1_rpc).await?;
+    let bridgehub_address = match config.bridgehub {
+        Some(address) => address,
+        None => select_bridgehub_address(
+            &detect_l2_sequencer(&config.l2_rpc).await,
+            &detect_l2_sequencer(&config.l3_rpc).await,
+        )?,
+    };
+    let options = BridgehubOptions {
+        resolve_ctms: false,
+        concurrency: config.concurrency,
+    };
+    let bridgehub = Bridgehub::new_with_options(&l1_sequencer, bridgehub_address, options).await?;
+    if !bridgehub.known_chains.contains(&chain_id) {
+        return Err(DebuggerError::ChainNotRegistered { chain_id }.into());
+    }
+
+    let balances = bridgehub
+        .get_chain_balances(&l1_sequencer, chain_id, config.concurrency)
+        .await?;
+    let symbols = bridgehub.token_symbols();
+    let decimals = bridgehub.token_decimals();
+    let mut tokens: Vec<_> = balances
+        .iter()
+        .map(|(token, amount)| {
+            let symbol = symbols.get(token).map(String::as_str);
+            TokenBalanceReport::new(token, "l1", *amount, symbol, decimals.get(token).copied())
+        })
+        .collect();
+    tokens.sort_by(|a, b| a.token.cmp(&b.token));
+    Ok(tokens)
+}
+
+// Prints the bridge balances of a single chain.
+pub async fn balances(args: &Cli, chain_id: u64, json: bool) -> eyre::Result<()> {
+    let tokens = chain_balances(&BalancesConfig::from_cli(args), chain_id).await?;
+    if json {
+        println!("{}", serde_json::to_string(&tokens)?);
+    } else {
+        println!("Balances of chain {}:", chain_to_human(chain_id));
+        for token in &tokens {
+            println!("  {:<30} : {:>28}", token.token, token.display);
+        }
+    }
+    Ok(())
+}
+
+// The raw value of a slot, and the ways it is commonly read.
+fn slot_interpretations(value: U256) -> Vec<(&'static str, String)> {
+    let bytes = B256::from(value);
+    let mut result = vec![
+        ("raw", bytes.to_string()),
+        ("address", u256_to_address(value).to_string()),
+        ("uint256", value.to_string()),
+        (
+            "bytes",
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    ];
+    if let Some(string) = short_string(&bytes) {
+        result.push(("string", format!("{:?}", string)));
+    }
+    result
+}
+
+// Solidity keeps strings shorter than 32 bytes in a single slot: the data left aligned and
+// `length * 2` in the last byte.
+fn short_string(bytes: &B256) -> Option<String> {
+    let encoded_length = bytes[31];
+    if encoded_length == 0 || encoded_length % 2 == 1 || encoded_length / 2 > 31 {
+        return None;
+    }
+    let data = &bytes[..(encoded_length / 2) as usize];
+    let string = std::str::from_utf8(data).ok()?;
+    string
+        .chars()
+        .all(|c| !c.is_control())
+        .then(|| string.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interprets_slot_holding_an_address() {
+        let value = U256::from_be_slice(Address::repeat_byte(0xab).as_slice());
+        let interpretations = slot_interpretations(value);
+
+        assert_eq!(
+            interpretations[0],
+            (
+                "raw",
+                "0x000000000000000000000000abababababababababababababababababababab".to_string()
+            )
+        );
+        assert_eq!(
+            interpretations[1],
+            ("address", Address::repeat_byte(0xab).to_string())
+        );
+        assert_eq!(interpretations[2], ("uint256", value.to_string()));
+        assert_eq!(interpretations.len(), 4);
+    }
+
+    #[test]
+    fn interprets_slot_holding_a_short_string() {
+        let mut bytes = [0u8; 32];
+        bytes[..3].copy_from_slice(b"ETH");
+        bytes[31] = 6;
+        let interpretations = slot_interpretations(U256::from_be_bytes(bytes));
+
+        assert_eq!(interpretations[4], ("string", "\"ETH\"".to_string()));
+    }
+}
@@ -0,0 +1,101 @@
+// Known failure categories, so that callers can match on the kind of an error (and read its
+// context) instead of parsing messages. Functions keep returning eyre::Result - these are
+// reached with `err.downcast_ref::<DebuggerError>()`.
+
+use alloy::primitives::{Address, U256};
+
+use crate::sequencer::SequencerError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DebuggerError {
+    // There is no contract at the address - usually a wrong address, or an rpc url
+    // pointing at a different chain.
+    #[error("Trying to read bridgehub data from address {address} at {rpc_url}, but code is empty. Is it a rigth address on right chain?")]
+    EmptyContractCode { address: Address, rpc_url: String },
+
+    #[error("Chain {chain_id} is not registered on any known settlement layer")]
+    ChainNotRegistered { chain_id: u64 },
+
+    // Sequencer detection failures (including endpoints that aren't zksync sequencers).
+    #[error(transparent)]
+    Sequencer(#[from] SequencerError),
+
+    #[error("Priority transactions are only available on L1 (chain {chain_id} is an L2)")]
+    PriorityTxOnL2 { chain_id: u64 },
+
+    // The priority transaction scan didn't cover the whole history, so the tree can't be verified.
+    #[error(
+        "Incomplete scan, cannot verify: fetched {fetched} of {total} priority transactions (highest index: {})",
+        .highest_index.map(|index| index.to_string()).unwrap_or("none".to_string())
+    )]
+    IncompletePriorityScan {
+        fetched: usize,
+        highest_index: Option<u64>,
+        total: U256,
+    },
+
+    #[error(
+        "Priority tree of {leaves} leaves is above the limit of {limit} - raise --max-merkle-leaves if the machine has the memory for it"
+    )]
+    MerkleTreeTooLarge { leaves: usize, limit: usize },
+
+    // The gateway is optional - failing to load it is recorded, and the run continues without it.
+    #[error("Gateway bridgehub {address} is not available: {reason}")]
+    GatewayUnavailable { address: Address, reason: String },
+
+    #[error(
+        "Protocol version {}.{}.{} has no priority tree (introduced in v{first_tree_version})",
+        .protocol_version.0, .protocol_version.1, .protocol_version.2
+    )]
+    NoPriorityTree {
+        protocol_version: (u32, u32, u32),
+        first_tree_version: u32,
+    },
+}
+
+impl DebuggerError {
+    // Short, stable identifier of the failure - used in the JSON report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DebuggerError::EmptyContractCode { .. } => "empty_contract_code",
+            DebuggerError::ChainNotRegistered { .. } => "chain_not_registered",
+            DebuggerError::Sequencer(err) => err.kind(),
+            DebuggerError::PriorityTxOnL2 { .. } => "priority_tx_on_l2",
+            DebuggerError::IncompletePriorityScan { .. } => "incomplete_priority_scan",
+            DebuggerError::NoPriorityTree { .. } => "no_priority_tree",
+            DebuggerError::GatewayUnavailable { .. } => "gateway_unavailable",
+            DebuggerError::MerkleTreeTooLarge { .. } => "merkle_tree_too_large",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_incomplete_scan() {
+        let err = DebuggerError::IncompletePriorityScan {
+            fetched: 2,
+            highest_index: None,
+            total: U256::from(5),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Incomplete scan, cannot verify: fetched 2 of 5 priority transactions (highest index: none)"
+        );
+        assert_eq!(err.kind(), "incomplete_priority_scan");
+    }
+
+    #[test]
+    fn sequencer_errors_keep_their_kind() {
+        let err = DebuggerError::from(SequencerError::NotAZkSyncSequencer {
+            rpc_url: "http://127.0.0.1:3050".to_string(),
+        });
+        assert_eq!(err.kind(), "not_a_zksync_sequencer");
+        assert_eq!(
+            err.to_string(),
+            "http://127.0.0.1:3050 is not a zksync sequencer"
+        );
+    }
+}
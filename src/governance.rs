@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use alloy::primitives::Address;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::chain_names::chain_to_human;
+
+// Expected admin / owner of a single chain or CTM.
+#[derive(Default, Deserialize)]
+pub struct ExpectedRoles {
+    pub admin: Option<Address>,
+    pub owner: Option<Address>,
+}
+
+// Governance that we expect to see on chain, loaded from `--expected-governance`:
+// `{"chains": {"324": {"admin": "0x.."}}, "ctms": {"0x..": {"admin": "0x..", "owner": "0x.."}}}`
+#[derive(Default, Deserialize)]
+pub struct ExpectedGovernance {
+    #[serde(default)]
+    pub chains: HashMap<u64, ExpectedRoles>,
+    #[serde(default)]
+    pub ctms: HashMap<Address, ExpectedRoles>,
+}
+
+// Audited verifier contracts, loaded from `--expected-verifiers`: `{"0x..": "Verifier v26"}`.
+#[derive(Default, Deserialize)]
+pub struct ExpectedVerifiers(HashMap<Address, String>);
+
+impl ExpectedVerifiers {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| eyre::eyre!("Invalid verifiers file {}: {}", path.display(), err))
+    }
+
+    // Name of the verifier, None if it is not one of the expected ones.
+    pub fn name_of(&self, verifier: Address) -> Option<&str> {
+        self.0.get(&verifier).map(String::as_str)
+    }
+}
+
+impl ExpectedGovernance {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| eyre::eyre!("Invalid governance file {}: {}", path.display(), err))
+    }
+
+    pub fn check_chain_admin(&self, chain_id: u64, actual: Address) -> GovernanceCheck {
+        GovernanceCheck::new(
+            format!("chain {}", chain_to_human(chain_id)),
+            "admin",
+            self.chains.get(&chain_id).and_then(|roles| roles.admin),
+            actual,
+        )
+    }
+
+    pub fn check_ctm(&self, ctm: Address, admin: Address, owner: Address) -> Vec<GovernanceCheck> {
+        let expected = self.ctms.get(&ctm);
+        vec![
+            GovernanceCheck::new(
+                format!("ctm {}", ctm),
+                "admin",
+                expected.and_then(|roles| roles.admin),
+                admin,
+            ),
+            GovernanceCheck::new(
+                format!("ctm {}", ctm),
+                "owner",
+                expected.and_then(|roles| roles.owner),
+                owner,
+            ),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+pub struct GovernanceCheck {
+    pub subject: String,
+    pub role: String,
+    pub expected: Option<String>,
+    pub actual: String,
+    // `ok`, `mismatch` or `unmonitored` (no expectation configured).
+    pub status: String,
+}
+
+impl GovernanceCheck {
+    fn new(subject: String, role: &str, expected: Option<Address>, actual: Address) -> Self {
+        let status = match expected {
+            Some(expected) if expected == actual => "ok",
+            Some(_) => "mismatch",
+            None => "unmonitored",
+        };
+        Self {
+            subject,
+            role: role.to_string(),
+            expected: expected.map(|address| format!("{:#x}", address)),
+            actual: format!("{:#x}", actual),
+            status: status.to_string(),
+        }
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        self.status == "mismatch"
+    }
+}
+
+impl Display for GovernanceCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status.as_str() {
+            "ok" => "[OK]".green(),
+            "mismatch" => "[MISMATCH]".red(),
+            _ => "[UNMONITORED]".yellow(),
+        };
+        write!(
+            f,
+            "{} {} {}: {}",
+            status, self.subject, self.role, self.actual
+        )?;
+        if self.is_mismatch() {
+            write!(
+                f,
+                " {}",
+                format!(
+                    "(expected {})",
+                    self.expected.as_deref().unwrap_or_default()
+                )
+                .red()
+            )?;
+        }
+        Ok(())
+    }
+}
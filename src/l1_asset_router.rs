@@ -1,11 +1,15 @@
 use std::{collections::HashMap, fmt::Display};
 
 use alloy::{
-    primitives::{address, Address, FixedBytes, U256},
+    primitives::{Address, FixedBytes, U256},
     sol,
 };
 
-use crate::{sequencer::Sequencer, utils::get_human_name_for};
+use crate::{
+    addresses::system_addresses,
+    sequencer::{Sequencer, SequencerProvider},
+    utils::get_human_name_for,
+};
 
 use colored::Colorize;
 
@@ -34,8 +38,47 @@ sol! {
     #[sol(rpc)]
     contract ERC20 {
         function name() external view returns(string);
+        function symbol() external view returns(string);
+        function decimals() external view returns(uint8);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    // None if the token doesn't implement decimals().
+    pub decimals: Option<u8>,
+}
 
+// Resolves ERC20 name & symbol of the token (or ETH for the native token sentinel).
+// Returns None if the address doesn't look like an ERC20.
+pub async fn resolve_token_metadata(
+    provider: &SequencerProvider,
+    token_address: Address,
+) -> Option<TokenMetadata> {
+    if token_address == system_addresses().eth_token {
+        return Some(TokenMetadata {
+            name: "ETH".to_owned(),
+            symbol: "ETH".to_owned(),
+            decimals: Some(18),
+        });
     }
+
+    let erc20_contract = ERC20::new(token_address, provider);
+    let name = erc20_contract.name().call().await.ok()?._0;
+    let symbol = erc20_contract.symbol().call().await.ok()?._0;
+    let decimals = erc20_contract
+        .decimals()
+        .call()
+        .await
+        .ok()
+        .map(|decimals| decimals._0);
+    Some(TokenMetadata {
+        name,
+        symbol,
+        decimals,
+    })
 }
 
 pub struct RegisteredAsset {
@@ -47,9 +90,13 @@ pub struct RegisteredAsset {
 pub struct NativeTokenVaultAsset {
     pub address: Address,
     pub token_name: String,
+    // ERC20 symbol and decimals, None if they couldn't be resolved.
+    pub token_symbol: Option<String>,
+    pub token_decimals: Option<u8>,
 }
 
 #[derive(Debug)]
+#[allow(dead_code)] // Only constructed by the (currently disabled) event based asset discovery.
 pub enum AssetHandler {
     Bridgehub,
     NativeTokenVault(NativeTokenVaultAsset),
@@ -69,6 +116,7 @@ impl AssetHandler {
 }
 
 impl RegisteredAsset {
+    #[allow(dead_code)]
     pub async fn new(
         sequencer: &Sequencer,
         asset_id: FixedBytes<32>,
@@ -77,8 +125,7 @@ impl RegisteredAsset {
         bridgehub: &Address,
     ) -> Self {
         let provider = sequencer.get_provider();
-        let native_token_vault_contract =
-            NativeTokenVault::new(native_token_vault.clone(), provider);
+        let native_token_vault_contract = NativeTokenVault::new(*native_token_vault, provider);
 
         let handler = match deployment_tracker {
             ref dt if dt == native_token_vault => {
@@ -89,27 +136,25 @@ impl RegisteredAsset {
                     .unwrap()
                     ._0;
 
-                let token_name =
-                    if token_address == address!("0000000000000000000000000000000000000001") {
-                        "ETH".to_owned()
-                    } else {
-                        let erc20_contract = ERC20::new(token_address, sequencer.get_provider());
-                        erc20_contract.name().call().await.unwrap()._0
-                    };
+                let metadata =
+                    resolve_token_metadata(&sequencer.get_provider(), token_address).await;
+                let token_name = metadata
+                    .as_ref()
+                    .map(|metadata| metadata.name.clone())
+                    .unwrap_or_else(|| "unknown".to_owned());
 
                 AssetHandler::NativeTokenVault(NativeTokenVaultAsset {
                     address: token_address,
                     token_name,
+                    token_decimals: metadata.as_ref().and_then(|metadata| metadata.decimals),
+                    token_symbol: metadata.map(|metadata| metadata.symbol),
                 })
             }
 
             ref dt if dt == bridgehub => AssetHandler::Bridgehub,
             _ => AssetHandler::Other(deployment_tracker),
         };
-        Self {
-            asset_id,
-            handler: handler,
-        }
+        Self { asset_id, handler }
     }
 
     pub fn name(&self) -> String {
@@ -124,6 +169,21 @@ impl RegisteredAsset {
         }
     }
 
+    // Symbol of the token, for the assets held in the native token vault.
+    pub fn symbol(&self) -> Option<&str> {
+        match &self.handler {
+            AssetHandler::NativeTokenVault(vault_asset) => vault_asset.token_symbol.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn decimals(&self) -> Option<u8> {
+        match &self.handler {
+            AssetHandler::NativeTokenVault(vault_asset) => vault_asset.token_decimals,
+            _ => None,
+        }
+    }
+
     pub fn detailed_fmt(&self, f: &mut std::fmt::Formatter<'_>, pad: usize) -> std::fmt::Result {
         let pad = " ".repeat(pad);
         writeln!(f, "{}Asset:     {}", pad, self.name().bold())?;
@@ -144,6 +204,8 @@ impl Display for RegisteredAsset {
 pub struct L1AssetRouter {
     pub address: Address,
     pub native_token_vault: Address,
+    // Bridgehub the router points back to, None if the getter reverted.
+    pub bridgehub: Option<Address>,
     pub registered_assets: HashMap<FixedBytes<32>, RegisteredAsset>,
 }
 impl Display for L1AssetRouter {
@@ -158,7 +220,12 @@ impl L1AssetRouter {
         let contract = IL1AssetRouter::new(address, provider);
 
         let native_token_vault = contract.nativeTokenVault().call().await?._0;
-        //let bridgehub = contract.BRIDGE_HUB().call().await.unwrap()._0;
+        let bridgehub = contract
+            .BRIDGE_HUB()
+            .call()
+            .await
+            .ok()
+            .map(|bridgehub| bridgehub._0);
 
         /*let registered_assets = get_all_events(
             sequencer,
@@ -190,10 +257,18 @@ impl L1AssetRouter {
         Ok(Self {
             address,
             native_token_vault,
+            bridgehub,
             registered_assets: HashMap::from_iter(registered_assets),
         })
     }
 
+    // Registered assets by name (then asset id), so that the output is stable across runs.
+    pub fn sorted_assets(&self) -> Vec<&RegisteredAsset> {
+        let mut assets: Vec<_> = self.registered_assets.values().collect();
+        assets.sort_by_cached_key(|asset| (asset.name(), asset.asset_id));
+        assets
+    }
+
     pub async fn chain_balance(
         &self,
         sequencer: &Sequencer,
@@ -225,10 +300,53 @@ impl L1AssetRouter {
             pad, self.native_token_vault
         )?;
         writeln!(f, "{}Assets: ", pad)?;
-        for v in self.registered_assets.values() {
+        for v in self.sorted_assets() {
             v.detailed_fmt(f, pad_size + 3)?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(id: u8, token_name: &str) -> (FixedBytes<32>, RegisteredAsset) {
+        let asset_id = FixedBytes::repeat_byte(id);
+        let handler = AssetHandler::NativeTokenVault(NativeTokenVaultAsset {
+            address: Address::repeat_byte(id),
+            token_name: token_name.to_string(),
+            token_symbol: Some(token_name.to_string()),
+            token_decimals: Some(18),
+        });
+        (asset_id, RegisteredAsset { asset_id, handler })
+    }
+
+    #[test]
+    fn sorts_assets_by_name_then_id() {
+        let router = L1AssetRouter {
+            address: Address::ZERO,
+            native_token_vault: Address::ZERO,
+            bridgehub: None,
+            registered_assets: HashMap::from([
+                asset(3, "USDC"),
+                asset(1, "ETH"),
+                asset(2, "DAI"),
+                asset(4, "ETH"),
+            ]),
+        };
+
+        let keys: Vec<(String, FixedBytes<32>)> = router
+            .sorted_assets()
+            .into_iter()
+            .map(|asset| (asset.name(), asset.asset_id))
+            .collect();
+        let mut expected = keys.clone();
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert_eq!(keys.len(), 4);
+        assert!(keys[0].0.starts_with("DAI-"));
+        assert!(keys[3].0.starts_with("USDC-"));
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 use alloy::{primitives::Address, sol};
 
+use colored::Colorize;
+
 use crate::sequencer::Sequencer;
 
 sol! {
@@ -14,7 +16,8 @@ sol! {
 // a.k.a SharedBridge
 pub struct L2AssetRouter {
     pub address: Address,
-    //pub l1_router: Address,
+    // None if the call reverted (older deployments don't expose it).
+    pub l1_router: Option<Address>,
 }
 impl Display for L2AssetRouter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -24,12 +27,17 @@ impl Display for L2AssetRouter {
 
 impl L2AssetRouter {
     pub async fn new(sequencer: &Sequencer, address: Address) -> Self {
-        //let provider = sequencer.get_provider();
-        //let contract = IL2AssetRouter::new(address, provider);
+        let provider = sequencer.get_provider();
+        let contract = IL2AssetRouter::new(address, provider);
 
-        //let l1_router = contract.l1AssetRouter().call().await.unwrap()._0;
+        let l1_router = contract
+            .l1AssetRouter()
+            .call()
+            .await
+            .ok()
+            .map(|result| result._0);
 
-        Self { address }
+        Self { address, l1_router }
     }
     pub fn detailed_fmt(
         &self,
@@ -38,7 +46,10 @@ impl L2AssetRouter {
     ) -> std::fmt::Result {
         let pad = " ".repeat(pad_size);
         writeln!(f, "{}=== L2 Asset -  {}  ", pad, self.address)?;
-        //writeln!(f, "{}   L1 router:   {}", pad, self.l1_router)?;
+        match self.l1_router {
+            Some(l1_router) => writeln!(f, "{}   L1 router:   {}", pad, l1_router)?,
+            None => writeln!(f, "{}   L1 router:   {}", pad, "unknown".yellow())?,
+        }
         Ok(())
     }
 }
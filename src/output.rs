@@ -0,0 +1,103 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// When set, the decorative progress output is suppressed (e.g. when the report goes to stdout).
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// println! that respects the quiet mode.
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+// print! that respects the quiet mode.
+macro_rules! progress_inline {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            print!($($arg)*);
+        }
+    };
+}
+
+// Position in the per chain collection, with the time remaining extrapolated from the average
+// duration of the chains finished so far.
+pub struct ChainProgress {
+    total: usize,
+    finished: usize,
+    elapsed: Duration,
+}
+
+impl ChainProgress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            finished: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn chain_finished(&mut self, duration: Duration) {
+        self.finished += 1;
+        self.elapsed += duration;
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.finished == 0 {
+            return None;
+        }
+        let left = self.total.saturating_sub(self.finished) as u32;
+        Some(self.elapsed / self.finished as u32 * left)
+    }
+}
+
+// e.g. `chain 5/40, ~2m remaining` for the chain that is about to start.
+impl Display for ChainProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chain {}/{}", self.finished + 1, self.total)?;
+        match self.remaining() {
+            Some(remaining) if remaining.as_secs() >= 60 => {
+                write!(f, ", ~{}m remaining", (remaining.as_secs() + 30) / 60)
+            }
+            Some(remaining) => write!(f, ", ~{}s remaining", remaining.as_secs()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_estimate_before_the_first_chain() {
+        let progress = ChainProgress::new(40);
+        assert_eq!(progress.remaining(), None);
+        assert_eq!(progress.to_string(), "chain 1/40");
+    }
+
+    #[test]
+    fn extrapolates_from_the_average_chain() {
+        let mut progress = ChainProgress::new(40);
+        for secs in [2, 4, 3, 3] {
+            progress.chain_finished(Duration::from_secs(secs));
+        }
+        assert_eq!(progress.remaining(), Some(Duration::from_secs(108)));
+        assert_eq!(progress.to_string(), "chain 5/40, ~2m remaining");
+
+        let mut progress = ChainProgress::new(3);
+        progress.chain_finished(Duration::from_secs(10));
+        assert_eq!(progress.to_string(), "chain 2/3, ~20s remaining");
+    }
+}
@@ -0,0 +1,97 @@
+// Approximate token prices, for the per chain TVL in ETH.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use alloy::primitives::U256;
+use serde::Deserialize;
+
+// Token symbol -> price in ETH, loaded from `--prices`: `{"ETH": 1, "USDC": 0.0004}`.
+#[derive(Default, Deserialize)]
+pub struct TokenPrices(HashMap<String, f64>);
+
+// Balance of a chain in ETH, summed over the tokens that have a price.
+pub struct ChainTvl {
+    pub tvl_eth: f64,
+    // Tokens without a configured price (or with unknown decimals) - not included in the sum.
+    pub unpriced_tokens: Vec<String>,
+}
+
+impl TokenPrices {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        serde_json::from_slice(&fs::read(path)?)
+            .map_err(|err| eyre::eyre!("Invalid prices file {}: {}", path.display(), err))
+    }
+
+    // Balances are keyed by `SYMBOL-asset_name`, the price is looked up by the symbol.
+    pub fn price_of(&self, token: &str) -> Option<f64> {
+        let (symbol, _) = token.split_once('-')?;
+        self.0.get(symbol).copied()
+    }
+
+    // Balances come with the decimals of their token (e.g. 6 for USDC), as the price is per
+    // whole token.
+    pub fn chain_tvl<'a>(
+        &self,
+        balances: impl IntoIterator<Item = (&'a str, U256, Option<u8>)>,
+    ) -> ChainTvl {
+        let mut tvl_eth = 0.0;
+        let mut unpriced_tokens = Vec::new();
+        for (token, amount, decimals) in balances {
+            match (self.price_of(token), decimals) {
+                (Some(price), Some(decimals)) => {
+                    let amount: f64 = amount.to_string().parse().unwrap_or(f64::MAX);
+                    tvl_eth += amount / 10f64.powi(decimals.into()) * price;
+                }
+                _ => unpriced_tokens.push(token.to_string()),
+            }
+        }
+        ChainTvl {
+            tvl_eth,
+            unpriced_tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices() -> TokenPrices {
+        TokenPrices(HashMap::from([
+            ("ETH".to_string(), 1.0),
+            ("USDC".to_string(), 0.0004),
+        ]))
+    }
+
+    #[test]
+    fn looks_up_price_by_symbol() {
+        let prices = prices();
+        assert_eq!(prices.price_of("USDC-brave_lion"), Some(0.0004));
+        assert_eq!(prices.price_of("DAI-brave_lion"), None);
+        assert_eq!(prices.price_of("brave_lion"), None);
+    }
+
+    #[test]
+    fn sums_priced_tokens_and_lists_the_rest() {
+        let ether = U256::from(10).pow(U256::from(18));
+        let usdc = U256::from(10).pow(U256::from(6));
+        let tvl = prices().chain_tvl([
+            ("ETH-calm_fox", ether * U256::from(2), Some(18)),
+            ("USDC-brave_lion", usdc * U256::from(5000), Some(6)),
+            ("DAI-quiet_owl", ether, Some(18)),
+        ]);
+
+        assert!((tvl.tvl_eth - 4.0).abs() < 1e-9);
+        assert_eq!(tvl.unpriced_tokens, vec!["DAI-quiet_owl".to_string()]);
+    }
+
+    #[test]
+    fn tokens_with_unknown_decimals_are_not_priced() {
+        let tvl = prices().chain_tvl([("USDC-brave_lion", U256::from(5000), None)]);
+
+        assert_eq!(tvl.tvl_eth, 0.0);
+        assert_eq!(tvl.unpriced_tokens, vec!["USDC-brave_lion".to_string()]);
+    }
+}
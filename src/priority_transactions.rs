@@ -1,8 +1,17 @@
 use std::collections::HashMap;
 use std::fmt::{Debug, Display};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
 
 use crate::addresses::{address_to_human, u256_to_address};
-use crate::{sequencer::Sequencer, utils::get_all_events};
+use crate::amounts::{format_amount, AmountFormat};
+use crate::error::DebuggerError;
+use crate::{
+    sequencer::Sequencer,
+    utils::{get_all_events, get_deployment_block, get_events_since},
+};
 use alloy::primitives::{keccak256, Address, B256, U256};
 use alloy::rpc::types::Log;
 use alloy::sol;
@@ -12,6 +21,7 @@ use lazy_static::lazy_static;
 use serde::Serialize;
 
 sol! {
+    #[derive(Default)]
     struct L2CanonicalTransaction {
         uint256 txType;
         uint256 from;
@@ -54,14 +64,39 @@ sol! {
 }
 
 lazy_static! {
-    static ref KNOWN_SIGNATURES: HashMap<String, String> = {
+    // Selector (hex, without 0x) -> method name. Extended at runtime with --abi-map.
+    static ref KNOWN_SIGNATURES: RwLock<HashMap<String, String>> = {
         let json_value = serde_json::from_slice(include_bytes!("data/abi_map.json")).unwrap();
         let pairs: HashMap<String, String> = serde_json::from_value(json_value).unwrap();
 
-        pairs
+        RwLock::new(pairs)
     };
 }
 
+// Merges `{"<selector>": "<signature>"}` entries over the embedded map - entries for the same
+// selector override the embedded ones. Selectors may be 0x prefixed.
+pub fn load_abi_map(path: &Path) -> eyre::Result<()> {
+    let entries: HashMap<String, String> = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|err| eyre::eyre!("Invalid abi map {}: {}", path.display(), err))?;
+    add_signatures(entries);
+    Ok(())
+}
+
+fn add_signatures(entries: HashMap<String, String>) {
+    let mut signatures = KNOWN_SIGNATURES.write().unwrap();
+    for (selector, signature) in entries {
+        let selector = selector.trim_start_matches("0x").to_lowercase();
+        signatures.insert(selector, signature);
+    }
+}
+
+// Known name of the method with this selector, or the selector itself.
+fn method_name(selector: &[u8]) -> String {
+    let selector = hex::encode(selector);
+    let signatures = KNOWN_SIGNATURES.read().unwrap();
+    signatures.get(&selector).cloned().unwrap_or(selector)
+}
+
 #[derive(Serialize)]
 pub struct PriorityTransactionReport {
     pub index: u64,
@@ -77,6 +112,16 @@ pub struct PriorityTransactionReport {
     pub max_priority_fee_per_gas: String,
     pub method: Option<String>,
     pub data: String,
+    // Bytecodes published with the transaction (contract deployments), by keccak256.
+    pub factory_deps_count: usize,
+    pub factory_deps: Vec<String>,
+    // The raw `reserved` slots (hex), with the meaning of the ones that the protocol defines
+    // for L1 -> L2 transactions.
+    pub reserved: [String; 4],
+    pub mint_value_wei: Option<String>,
+    pub refund_recipient: Option<String>,
+    // Set when the L1 base fee at report time is known.
+    pub max_fee_below_l1_base_fee: Option<bool>,
 }
 
 pub struct PriorityTransaction {
@@ -84,6 +129,8 @@ pub struct PriorityTransaction {
     tx_id: B256,
     expiration_timestamp: u64,
     l2_tx: L2CanonicalTransaction,
+    // keccak256 of the raw factory dependencies (bytecodes) sent along with the transaction.
+    factory_deps: Vec<B256>,
 }
 
 impl Debug for PriorityTransaction {
@@ -102,27 +149,25 @@ impl Display for PriorityTransaction {
     }
 }
 
-fn format_integer_with_underscores(input: &str) -> String {
-    let reversed_input: String = input.chars().rev().collect();
+// Transaction type of the L1 -> L2 (priority) transactions.
+const PRIORITY_OPERATION_L2_TX_TYPE: u64 = 255;
 
-    // Insert underscores every three characters
-    let mut formatted = String::new();
-    for (index, char) in reversed_input.chars().enumerate() {
-        if index % 3 == 0 && index != 0 {
-            formatted.push('_');
-        }
-        formatted.push(char);
+impl PriorityTransaction {
+    // For L1 -> L2 transactions reserved[0] is the ETH minted on L2 (the value plus the fee)
+    // and reserved[1] the refund recipient - the other two slots are unused.
+    fn is_l1_to_l2(&self) -> bool {
+        self.l2_tx.txType == U256::from(PRIORITY_OPERATION_L2_TX_TYPE)
     }
 
-    // Reverse the formatted string to correct the order
-    formatted.chars().rev().collect()
-}
+    fn mint_value(&self) -> Option<U256> {
+        self.is_l1_to_l2().then_some(self.l2_tx.reserved[0])
+    }
 
-pub fn wei_as_string(value: U256) -> String {
-    format_integer_with_underscores(&value.to_string())
-}
+    fn refund_recipient(&self) -> Option<Address> {
+        self.is_l1_to_l2()
+            .then(|| u256_to_address(self.l2_tx.reserved[1]))
+    }
 
-impl PriorityTransaction {
     pub fn detailed_fmt(&self, f: &mut std::fmt::Formatter<'_>, pad: usize) -> std::fmt::Result {
         let pad = " ".repeat(pad);
         writeln!(f, "{}Tx: {} - {}", pad, self.index, self.tx_id)?;
@@ -136,27 +181,70 @@ impl PriorityTransaction {
         )?;
 
         if self.l2_tx.data.len() > 4 {
-            let selector = hex::encode(&self.l2_tx.data[0..4]);
-            let entry = KNOWN_SIGNATURES.get(&selector).unwrap_or(&selector);
+            let entry = method_name(&self.l2_tx.data[0..4]);
 
             writeln!(f, "{}    Method           - {}", pad, entry.bold())?;
         }
 
-        if self.l2_tx.reserved[0] > U256::ZERO {
+        if let Some(mint_value) = self.mint_value().filter(|value| *value > U256::ZERO) {
+            writeln!(
+                f,
+                "{}    Mint value       - {}",
+                pad,
+                format_amount(mint_value, AmountFormat::GroupedWei)
+            )?;
+        }
+        if let Some(recipient) = self.refund_recipient().filter(|a| *a != Address::ZERO) {
+            writeln!(
+                f,
+                "{}    Refund recipient - {}",
+                pad,
+                address_to_human(&recipient)
+            )?;
+        }
+        // Slots without a known meaning are only shown when set.
+        let unlabeled = if self.is_l1_to_l2() { 2 } else { 0 };
+        for (slot, value) in self.l2_tx.reserved.iter().enumerate().skip(unlabeled) {
+            if *value > U256::ZERO {
+                writeln!(f, "{}    Reserved[{}]      - {:#x}", pad, slot, value)?;
+            }
+        }
+
+        if !self.factory_deps.is_empty() {
             writeln!(
                 f,
-                "{}    Value (reserved) - {}",
+                "{}    Factory deps     - {}",
                 pad,
-                wei_as_string(self.l2_tx.reserved[0])
+                self.factory_deps.len().to_string().bold()
             )?;
+            for hash in &self.factory_deps {
+                writeln!(f, "{}      {}", pad, hash)?;
+            }
         }
         Ok(())
     }
 
+    pub fn tx_id(&self) -> B256 {
+        self.tx_id
+    }
+
+    pub fn expiration_timestamp(&self) -> u64 {
+        self.expiration_timestamp
+    }
+
+    pub fn max_fee_per_gas(&self) -> U256 {
+        self.l2_tx.maxFeePerGas
+    }
+
+    // Whether the L2 sender / target match the given ones (None matches anything).
+    pub fn matches(&self, from: Option<Address>, to: Option<Address>) -> bool {
+        from.is_none_or(|from| u256_to_address(self.l2_tx.from) == from)
+            && to.is_none_or(|to| u256_to_address(self.l2_tx.to) == to)
+    }
+
     pub fn to_report(&self) -> PriorityTransactionReport {
         let method = if self.l2_tx.data.len() > 4 {
-            let selector = hex::encode(&self.l2_tx.data[0..4]);
-            Some(KNOWN_SIGNATURES.get(&selector).cloned().unwrap_or(selector))
+            Some(method_name(&self.l2_tx.data[0..4]))
         } else {
             None
         };
@@ -168,38 +256,166 @@ impl PriorityTransaction {
             from: address_to_human(&u256_to_address(self.l2_tx.from)),
             to: address_to_human(&u256_to_address(self.l2_tx.to)),
             value_wei: self.l2_tx.value.to_string(),
-            value_formatted: wei_as_string(self.l2_tx.value),
+            value_formatted: format_amount(self.l2_tx.value, AmountFormat::GroupedWei),
             gas_limit: self.l2_tx.gasLimit.to_string(),
             gas_per_pubdata_byte_limit: self.l2_tx.gasPerPubdataByteLimit.to_string(),
             max_fee_per_gas: self.l2_tx.maxFeePerGas.to_string(),
             max_priority_fee_per_gas: self.l2_tx.maxPriorityFeePerGas.to_string(),
             method,
             data: format!("0x{}", hex::encode(&self.l2_tx.data)),
+            factory_deps_count: self.factory_deps.len(),
+            factory_deps: self
+                .factory_deps
+                .iter()
+                .map(|hash| format!("{:#x}", hash))
+                .collect(),
+            reserved: self.l2_tx.reserved.map(|value| format!("{:#x}", value)),
+            mint_value_wei: self.mint_value().map(|value| value.to_string()),
+            refund_recipient: self
+                .refund_recipient()
+                .map(|address| format!("{:#x}", address)),
+            max_fee_below_l1_base_fee: None,
         }
     }
 }
 
-impl From<Log> for PriorityTransaction {
-    fn from(value: Log) -> Self {
-        let request =
-            IMailbox::NewPriorityRequest::abi_decode_data(&value.data().data, true).unwrap();
+impl TryFrom<Log> for PriorityTransaction {
+    type Error = eyre::Report;
 
-        let index: u64 = request.0.try_into().unwrap();
+    fn try_from(value: Log) -> eyre::Result<Self> {
+        let request = IMailbox::NewPriorityRequest::abi_decode_data(&value.data().data, true)?;
+
+        let index: u64 = request
+            .0
+            .try_into()
+            .map_err(|_| eyre::eyre!("Priority tx id {} doesn't fit into u64", request.0))?;
         let tx_id = request.1;
         let expiration_timestamp = request.2;
+        let factory_deps = request.4.iter().map(keccak256).collect();
 
-        Self {
+        Ok(Self {
             index,
             tx_id,
             expiration_timestamp,
             l2_tx: request.3,
+            factory_deps,
+        })
+    }
+}
+
+// Priority transactions decoded from the NewPriorityRequest logs.
+pub struct FetchedPriorityTransactions {
+    pub txs: Vec<PriorityTransaction>,
+    // Logs that couldn't be decoded (malformed / unexpected data), left out of `txs`.
+    pub skipped_logs: usize,
+}
+
+// The priority transaction with the given hash (the L2 hash of an L1 -> L2 transaction).
+pub fn find_by_tx_id(txs: &[PriorityTransaction], tx_id: B256) -> Option<&PriorityTransaction> {
+    txs.iter().find(|tx| tx.tx_id == tx_id)
+}
+
+// Decodes the logs, skipping (with a warning) the ones that fail to decode.
+pub fn decode_priority_logs(logs: Vec<Log>) -> FetchedPriorityTransactions {
+    let mut txs = Vec::new();
+    let mut skipped_logs = 0;
+    for log in logs {
+        let (block, log_index) = (log.block_number, log.log_index);
+        match PriorityTransaction::try_from(log) {
+            Ok(tx) => txs.push(tx),
+            Err(err) => {
+                progress!(
+                    "{} Skipping priority log (block {:?}, log index {:?}) that failed to decode: {}",
+                    "[WARN]".yellow(),
+                    block,
+                    log_index,
+                    err
+                );
+                skipped_logs += 1;
+            }
+        }
+    }
+    FetchedPriorityTransactions { txs, skipped_logs }
+}
+
+// Decodes a NewPriorityRequest log serialized as JSON (e.g. copied from a block explorer).
+pub fn decode_priority_log_json(json: &[u8]) -> eyre::Result<PriorityTransaction> {
+    let log: Log = serde_json::from_slice(json)?;
+    if log.topic0() != Some(&IMailbox::NewPriorityRequest::SIGNATURE_HASH) {
+        eyre::bail!("Not a NewPriorityRequest log");
+    }
+    PriorityTransaction::try_from(log)
+}
+
+// How the leaves and nodes of the priority tree are hashed. Chosen by the protocol version
+// of the chain, as newer versions may change the tree layout:
+//   * < v26 - no priority tree (only the legacy priority queue), nothing to verify.
+//   * v26+  - Keccak256: empty leaves are keccak256(""), nodes are keccak256(left || right).
+// When a new version changes the scheme, add a variant here and a match arm below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriorityTreeHashing {
+    Keccak256,
+}
+
+impl PriorityTreeHashing {
+    // Protocol version in which the priority tree was introduced.
+    pub const FIRST_TREE_VERSION: u32 = 26;
+
+    // `protocol_version` is the (major, minor, patch) semver, the minor being the
+    // "protocol version" as it is usually referred to (v26 etc).
+    pub fn for_protocol_version(protocol_version: (u32, u32, u32)) -> Option<Self> {
+        match protocol_version {
+            (0, minor, _) if minor < Self::FIRST_TREE_VERSION => None,
+            _ => Some(PriorityTreeHashing::Keccak256),
+        }
+    }
+
+    fn empty_leaf(&self) -> B256 {
+        match self {
+            PriorityTreeHashing::Keccak256 => keccak256(""),
+        }
+    }
+
+    fn hash_nodes(&self, left: &B256, right: &B256) -> B256 {
+        match self {
+            PriorityTreeHashing::Keccak256 => {
+                keccak256([left.as_slice(), right.as_slice()].concat())
+            }
         }
     }
 }
 
-pub fn compute_merkle_tree(txs: &Vec<PriorityTransaction>) -> B256 {
+// The tree is kept in memory as a vector of the padded (power of two) size - above this many
+// leaves the computation is refused rather than risking an OOM. Set with --max-merkle-leaves.
+pub const DEFAULT_MAX_MERKLE_LEAVES: usize = 1 << 22;
+static MAX_MERKLE_LEAVES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_MERKLE_LEAVES);
+
+pub fn set_max_merkle_leaves(limit: usize) {
+    MAX_MERKLE_LEAVES.store(limit, Ordering::Relaxed);
+}
+
+// Checks that a tree of `count` transactions can be computed, warning about the cost when it
+// is above a quarter of the limit.
+pub fn check_merkle_tree_size(count: usize) -> Result<(), DebuggerError> {
+    let limit = MAX_MERKLE_LEAVES.load(Ordering::Relaxed);
+    let leaves = count.checked_next_power_of_two().unwrap_or(usize::MAX);
+    if leaves > limit {
+        return Err(DebuggerError::MerkleTreeTooLarge { leaves, limit });
+    }
+    if leaves > limit / 4 {
+        progress!(
+            "{} Computing the priority tree over {} leaves (~{} MB), this may take a while",
+            "[WARN]".yellow(),
+            leaves,
+            leaves * 32 / (1 << 20)
+        );
+    }
+    Ok(())
+}
+
+pub fn compute_merkle_tree(txs: &Vec<PriorityTransaction>, hashing: PriorityTreeHashing) -> B256 {
     let size = txs.len().next_power_of_two();
-    let mut leaves = vec![keccak256(""); size];
+    let mut leaves = vec![hashing.empty_leaf(); size];
     for tx in txs {
         leaves[tx.index as usize] = tx.tx_id;
     }
@@ -207,39 +423,351 @@ pub fn compute_merkle_tree(txs: &Vec<PriorityTransaction>) -> B256 {
         let mut parents = vec![];
 
         for i in 0..(leaves.len() / 2) {
-            let payload = [leaves[2 * i].as_slice(), leaves[2 * i + 1].as_slice()].concat();
-
-            parents.push(keccak256(payload));
+            parents.push(hashing.hash_nodes(&leaves[2 * i], &leaves[2 * i + 1]));
         }
         leaves = parents;
     }
 
-    *leaves.get(0).unwrap()
+    *leaves.first().unwrap()
+}
+
+// Blocks that the NewPriorityRequest events are scanned in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorityScanRange {
+    // The last 5000 blocks - enough for listing the recent transactions in the report.
+    Recent,
+    // Everything since the deployment of the chain - only needed to verify the priority tree,
+    // as it costs many (archive) requests per chain.
+    FullHistory,
 }
 
-pub async fn fetch_all_priority_transactions(
+pub async fn fetch_priority_transactions(
     sequencer: &Sequencer,
     address: Address,
-) -> eyre::Result<Vec<PriorityTransaction>> {
+    range: PriorityScanRange,
+) -> eyre::Result<FetchedPriorityTransactions> {
     match sequencer.sequencer_type {
         crate::sequencer::SequencerType::L1 => {
-            let events = get_all_events(
-                sequencer,
-                address,
-                IMailbox::NewPriorityRequest::SIGNATURE_HASH,
-                5000, // 5k block limit
-            )
-            .await
-            .unwrap();
-            let txs: Vec<PriorityTransaction> = events
-                .into_iter()
-                .map(|x| PriorityTransaction::from(x))
-                .collect();
-
-            Ok(txs)
+            let signature = IMailbox::NewPriorityRequest::SIGNATURE_HASH;
+            // Without an archive node the deployment block can't be found, and only the recent
+            // blocks can be scanned.
+            let deployment_block = match range {
+                PriorityScanRange::Recent => None,
+                PriorityScanRange::FullHistory => {
+                    Some(get_deployment_block(sequencer, address).await)
+                }
+            };
+            let events = match deployment_block {
+                None => get_all_events(sequencer, address, signature, 5000).await?, // 5k block limit
+                Some(Ok(deployment_block)) => {
+                    get_events_since(sequencer, address, signature, deployment_block).await?
+                }
+                Some(Err(err)) => {
+                    progress!(
+                        "{} Deployment block of {} not found, scanning only the recent blocks: {}",
+                        "[WARN]".yellow(),
+                        address,
+                        err
+                    );
+                    get_all_events(sequencer, address, signature, 5000).await? // 5k block limit
+                }
+            };
+
+            Ok(decode_priority_logs(events))
+        }
+        crate::sequencer::SequencerType::L2(_) => Err(DebuggerError::PriorityTxOnL2 {
+            chain_id: sequencer.chain_id,
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::sort_and_dedup_logs;
+
+    const HASHING: PriorityTreeHashing = PriorityTreeHashing::Keccak256;
+
+    fn tx(index: u64) -> PriorityTransaction {
+        PriorityTransaction {
+            index,
+            tx_id: keccak256(index.to_be_bytes()),
+            expiration_timestamp: 0,
+            l2_tx: L2CanonicalTransaction::default(),
+            factory_deps: vec![],
         }
-        crate::sequencer::SequencerType::L2(_) => {
-            eyre::bail!("Priority transactions are only available on L1");
+    }
+
+    #[test]
+    fn finds_transactions_by_hash() {
+        let txs = vec![tx(3), tx(4), tx(5)];
+
+        let found = find_by_tx_id(&txs, keccak256(4u64.to_be_bytes())).unwrap();
+        assert_eq!(found.index, 4);
+        assert!(find_by_tx_id(&txs, B256::ZERO).is_none());
+    }
+
+    #[test]
+    fn labels_reserved_slots_of_l1_to_l2_transactions() {
+        let mut l1_to_l2 = tx(0);
+        l1_to_l2.l2_tx.txType = U256::from(PRIORITY_OPERATION_L2_TX_TYPE);
+        l1_to_l2.l2_tx.reserved = [U256::from(1000), U256::from(0x42), U256::ZERO, U256::ZERO];
+
+        let report = l1_to_l2.to_report();
+        assert_eq!(report.reserved, ["0x3e8", "0x42", "0x0", "0x0"]);
+        assert_eq!(report.mint_value_wei.as_deref(), Some("1000"));
+        assert_eq!(
+            report.refund_recipient.as_deref(),
+            Some("0x0000000000000000000000000000000000000042")
+        );
+
+        let mut other = tx(1);
+        other.l2_tx.reserved[0] = U256::from(1000);
+        let report = other.to_report();
+        assert_eq!(report.reserved[0], "0x3e8");
+        assert!(report.mint_value_wei.is_none());
+        assert!(report.refund_recipient.is_none());
+    }
+
+    fn priority_request_log(index: u64, block: u64, log_index: u64) -> Log {
+        let event = IMailbox::NewPriorityRequest {
+            txId: U256::from(index),
+            txHash: keccak256(index.to_be_bytes()),
+            expirationTimestamp: 0,
+            transaction: L2CanonicalTransaction::default(),
+            factoryDeps: vec![],
+        };
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(block),
+            log_index: Some(log_index),
+            ..Default::default()
         }
     }
+
+    fn hash_pair(left: B256, right: B256) -> B256 {
+        keccak256([left.as_slice(), right.as_slice()].concat())
+    }
+
+    fn empty_leaf() -> B256 {
+        keccak256("")
+    }
+
+    #[test]
+    fn decodes_serialized_priority_log() {
+        let json = serde_json::to_vec(&priority_request_log(7, 100, 0)).unwrap();
+
+        let tx = decode_priority_log_json(&json).unwrap();
+
+        assert_eq!(tx.index, 7);
+        assert_eq!(tx.tx_id, keccak256(7u64.to_be_bytes()));
+    }
+
+    #[test]
+    fn hashes_factory_deps() {
+        let mut log = priority_request_log(3, 100, 0);
+        let event = IMailbox::NewPriorityRequest {
+            txId: U256::from(3),
+            txHash: keccak256(3u64.to_be_bytes()),
+            expirationTimestamp: 0,
+            transaction: L2CanonicalTransaction::default(),
+            factoryDeps: vec![vec![1u8; 32].into(), vec![2u8; 64].into()],
+        };
+        log.inner.data = event.encode_log_data();
+
+        let report = PriorityTransaction::try_from(log).unwrap().to_report();
+
+        assert_eq!(report.factory_deps_count, 2);
+        assert_eq!(
+            report.factory_deps,
+            vec![
+                format!("{:#x}", keccak256([1u8; 32])),
+                format!("{:#x}", keccak256([2u8; 64]))
+            ]
+        );
+        assert_eq!(tx(0).to_report().factory_deps_count, 0);
+    }
+
+    #[test]
+    fn rejects_other_logs() {
+        let mut log = priority_request_log(7, 100, 0);
+        log.inner.data = alloy::primitives::LogData::new_unchecked(vec![], Default::default());
+        let json = serde_json::to_vec(&log).unwrap();
+
+        assert!(decode_priority_log_json(&json).is_err());
+    }
+
+    #[test]
+    fn skips_logs_that_fail_to_decode() {
+        let mut truncated = priority_request_log(1, 100, 1);
+        let topics = truncated.inner.data.topics().to_vec();
+        let data = truncated.inner.data.data[..64].to_vec();
+        truncated.inner.data = alloy::primitives::LogData::new_unchecked(topics, data.into());
+
+        assert!(PriorityTransaction::try_from(truncated.clone()).is_err());
+
+        let fetched = decode_priority_logs(vec![
+            priority_request_log(0, 100, 0),
+            truncated,
+            priority_request_log(2, 100, 2),
+        ]);
+        let indices: Vec<u64> = fetched.txs.iter().map(|tx| tx.index).collect();
+        assert_eq!(indices, vec![0, 2]);
+        assert_eq!(fetched.skipped_logs, 1);
+    }
+
+    #[test]
+    fn external_signatures_override_embedded_ones() {
+        assert_eq!(
+            method_name(&hex::decode("715018a6").unwrap()),
+            "renounceOwnership"
+        );
+        assert_eq!(method_name(&[0xde, 0xad, 0xbe, 0xef]), "deadbeef");
+
+        add_signatures(HashMap::from([
+            ("0xDEADBEEF".to_string(), "deadBeef(uint256)".to_string()),
+            (
+                "f4943a20".to_string(),
+                "protocolVersionDeadline(uint256)".to_string(),
+            ),
+        ]));
+
+        assert_eq!(method_name(&[0xde, 0xad, 0xbe, 0xef]), "deadBeef(uint256)");
+        assert_eq!(
+            method_name(&hex::decode("f4943a20").unwrap()),
+            "protocolVersionDeadline(uint256)"
+        );
+    }
+
+    #[test]
+    fn refuses_merkle_trees_above_the_limit() {
+        assert!(check_merkle_tree_size(DEFAULT_MAX_MERKLE_LEAVES).is_ok());
+        let err = check_merkle_tree_size(DEFAULT_MAX_MERKLE_LEAVES + 1).unwrap_err();
+        assert_eq!(err.kind(), "merkle_tree_too_large");
+        assert!(matches!(
+            err,
+            DebuggerError::MerkleTreeTooLarge { leaves, .. } if leaves == 2 * DEFAULT_MAX_MERKLE_LEAVES
+        ));
+        // Queue sizes that don't fit a tree at all (checked before fetching).
+        assert!(check_merkle_tree_size(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn merkle_tree_of_no_transactions_is_the_empty_leaf() {
+        assert_eq!(compute_merkle_tree(&vec![], HASHING), empty_leaf());
+    }
+
+    #[test]
+    fn merkle_tree_of_single_transaction_is_its_hash() {
+        let txs = vec![tx(0)];
+        assert_eq!(compute_merkle_tree(&txs, HASHING), txs[0].tx_id);
+    }
+
+    #[test]
+    fn merkle_tree_of_two_transactions() {
+        let txs = vec![tx(0), tx(1)];
+        assert_eq!(
+            compute_merkle_tree(&txs, HASHING),
+            hash_pair(txs[0].tx_id, txs[1].tx_id)
+        );
+    }
+
+    #[test]
+    fn merkle_tree_pads_up_to_power_of_two() {
+        let txs = vec![tx(0), tx(1), tx(2)];
+        let expected = hash_pair(
+            hash_pair(txs[0].tx_id, txs[1].tx_id),
+            hash_pair(txs[2].tx_id, empty_leaf()),
+        );
+        assert_eq!(compute_merkle_tree(&txs, HASHING), expected);
+    }
+
+    #[test]
+    fn merkle_tree_of_exact_power_of_two() {
+        let txs: Vec<_> = (0..4).map(tx).collect();
+        let expected = hash_pair(
+            hash_pair(txs[0].tx_id, txs[1].tx_id),
+            hash_pair(txs[2].tx_id, txs[3].tx_id),
+        );
+        assert_eq!(compute_merkle_tree(&txs, HASHING), expected);
+    }
+
+    #[test]
+    fn merkle_tree_just_above_power_of_two() {
+        let txs: Vec<_> = (0..5).map(tx).collect();
+        let empty_pair = hash_pair(empty_leaf(), empty_leaf());
+        let expected = hash_pair(
+            hash_pair(
+                hash_pair(txs[0].tx_id, txs[1].tx_id),
+                hash_pair(txs[2].tx_id, txs[3].tx_id),
+            ),
+            hash_pair(hash_pair(txs[4].tx_id, empty_leaf()), empty_pair),
+        );
+        assert_eq!(compute_merkle_tree(&txs, HASHING), expected);
+    }
+
+    #[test]
+    fn merkle_tree_places_transactions_by_index() {
+        let txs = vec![tx(1), tx(0)];
+        assert_eq!(
+            compute_merkle_tree(&txs, HASHING),
+            hash_pair(txs[1].tx_id, txs[0].tx_id)
+        );
+    }
+
+    #[test]
+    fn hashing_scheme_by_protocol_version() {
+        assert_eq!(PriorityTreeHashing::for_protocol_version((0, 24, 2)), None);
+        assert_eq!(PriorityTreeHashing::for_protocol_version((0, 25, 1)), None);
+        assert_eq!(
+            PriorityTreeHashing::for_protocol_version((0, 26, 0)),
+            Some(PriorityTreeHashing::Keccak256)
+        );
+        assert_eq!(
+            PriorityTreeHashing::for_protocol_version((0, 28, 0)),
+            Some(PriorityTreeHashing::Keccak256)
+        );
+    }
+
+    #[test]
+    fn matches_sender_and_target() {
+        let from = Address::repeat_byte(0x11);
+        let to = Address::repeat_byte(0x22);
+        let mut transaction = tx(0);
+        transaction.l2_tx.from = U256::from_be_slice(from.as_slice());
+        transaction.l2_tx.to = U256::from_be_slice(to.as_slice());
+
+        assert!(transaction.matches(None, None));
+        assert!(transaction.matches(Some(from), None));
+        assert!(transaction.matches(Some(from), Some(to)));
+        assert!(!transaction.matches(Some(to), None));
+        assert!(!transaction.matches(Some(from), Some(from)));
+    }
+
+    #[test]
+    fn overlapping_event_windows_give_unique_transactions() {
+        // Windows are fetched newest first; the boundary block 1000 is returned twice.
+        let newer_window = vec![
+            priority_request_log(2, 1000, 0),
+            priority_request_log(3, 1200, 5),
+        ];
+        let older_window = vec![
+            priority_request_log(0, 600, 1),
+            priority_request_log(1, 600, 2),
+            priority_request_log(2, 1000, 0),
+        ];
+        let logs = sort_and_dedup_logs([newer_window, older_window].concat());
+
+        let txs = decode_priority_logs(logs).txs;
+        let indices: Vec<u64> = txs.iter().map(|tx| tx.index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        assert_eq!(
+            compute_merkle_tree(&txs, HASHING),
+            compute_merkle_tree(&(0..4).map(tx).collect(), HASHING)
+        );
+    }
 }
@@ -1,20 +1,68 @@
-use std::{fmt::Display, net::TcpStream, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
 
 use alloy::{
+    eips::BlockNumberOrTag,
     hex::FromHex,
-    primitives::Address,
-    providers::{Provider, ProviderBuilder, RootProvider},
-    transports::http::{reqwest::Response, Client, Http},
+    primitives::{Address, B256},
+    providers::{Provider, RootProvider},
+    rpc::client::RpcClient,
+    transports::http::{
+        reqwest::{Response, Url},
+        Client,
+    },
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use crate::transport::FailoverTransport;
+use lazy_static::lazy_static;
+
+static CACHE_DETECTION: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    // Successfully detected sequencers by their (comma separated) rpc urls, filled only when
+    // caching is enabled (e.g. in watch mode, where the same urls are detected on every tick).
+    static ref DETECTED: Mutex<HashMap<String, Sequencer>> = Mutex::new(HashMap::new());
+}
+
+pub fn set_cache_detection(cache: bool) {
+    CACHE_DETECTION.store(cache, Ordering::Relaxed);
+}
+
+pub type SequencerProvider = RootProvider<FailoverTransport>;
+
 #[derive(Clone, Serialize)]
 pub struct Sequencer {
+    #[serde(serialize_with = "serialize_redacted_url")]
     pub rpc_url: String,
+    // All the urls of this sequencer (the one that responded during detection first),
+    // requests fail over between them.
+    #[serde(skip)]
+    pub rpc_urls: Vec<String>,
+    // Urls used only for fetching logs (e.g. an archive node), empty to use `rpc_urls`.
+    #[serde(skip)]
+    pub archive_rpc_urls: Vec<String>,
     pub chain_id: u64,
     pub latest_block: u64,
+    // Block that all the reads are done at, so that the report is a consistent snapshot.
+    // Private, so that it can't change without resetting the cached providers.
+    pinned_block: Option<u64>,
     pub sequencer_type: SequencerType,
+    // Built on first use and cloned afterwards (clones share the connection pool). Reset
+    // whenever the urls or the pinned block change.
+    #[serde(skip)]
+    provider: OnceLock<SequencerProvider>,
+    #[serde(skip)]
+    archive_provider: OnceLock<SequencerProvider>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -29,6 +77,71 @@ pub struct L2SequencerInfo {
     pub bridgehub_address: Address,
 }
 
+// Reasons why a sequencer could not be detected at a given url.
+#[derive(Debug)]
+pub enum SequencerError {
+    PortNotActive { rpc_url: String },
+    InvalidUrl { rpc_url: String, reason: String },
+    ChainIdFetchFailed { rpc_url: String, reason: String },
+    RpcFailed { rpc_url: String, reason: String },
+    NotAZkSyncSequencer { rpc_url: String },
+}
+
+impl SequencerError {
+    // Short, stable identifier of the failure - used in the JSON report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SequencerError::PortNotActive { .. } => "port_not_active",
+            SequencerError::InvalidUrl { .. } => "invalid_url",
+            SequencerError::ChainIdFetchFailed { .. } => "chain_id_fetch_failed",
+            SequencerError::RpcFailed { .. } => "rpc_failed",
+            SequencerError::NotAZkSyncSequencer { .. } => "not_a_zksync_sequencer",
+        }
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        match self {
+            SequencerError::PortNotActive { rpc_url }
+            | SequencerError::InvalidUrl { rpc_url, .. }
+            | SequencerError::ChainIdFetchFailed { rpc_url, .. }
+            | SequencerError::RpcFailed { rpc_url, .. }
+            | SequencerError::NotAZkSyncSequencer { rpc_url } => rpc_url,
+        }
+    }
+}
+
+// Urls usually carry API keys, so only the redacted ones end up in the report.
+fn serialize_redacted_url<S: serde::Serializer>(
+    url: &str,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&crate::redact_urls(url))
+}
+
+impl Display for SequencerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SequencerError::PortNotActive { rpc_url } => {
+                write!(f, "Port not active: {}", rpc_url)
+            }
+            SequencerError::InvalidUrl { rpc_url, reason } => {
+                write!(f, "Invalid rpc url {}: {}", rpc_url, reason)
+            }
+            SequencerError::ChainIdFetchFailed { rpc_url, reason } => {
+                write!(f, "Failed to fetch chain id from {}: {}", rpc_url, reason)
+            }
+            SequencerError::RpcFailed { rpc_url, reason } => {
+                write!(f, "Request to {} failed: {}", rpc_url, reason)
+            }
+            SequencerError::NotAZkSyncSequencer { rpc_url } => {
+                write!(f, "{} is not a zksync sequencer", rpc_url)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SequencerError {}
+
 impl Display for Sequencer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let sequencer_type_short = match &self.sequencer_type {
@@ -43,39 +156,136 @@ impl Display for Sequencer {
     }
 }
 
+// Fee market conditions at the time of the run.
+#[derive(Clone, Debug)]
+pub struct FeeContext {
+    pub block_number: u64,
+    pub gas_price: u128,
+    pub base_fee: Option<u128>,
+}
+
 impl Sequencer {
-    pub fn get_provider(&self) -> RootProvider<Http<Client>> {
-        let provider: alloy::providers::RootProvider<
-            alloy::transports::http::Http<alloy::transports::http::Client>,
-        > = ProviderBuilder::new().on_http(self.rpc_url.parse().unwrap());
+    pub async fn get_fee_context(&self) -> eyre::Result<FeeContext> {
+        let provider = self.get_provider();
+        let gas_price = provider.get_gas_price().await?;
+        let block_tag = self
+            .pinned_block
+            .map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number);
+        let block = provider
+            .get_block_by_number(block_tag, false)
+            .await?
+            .ok_or_else(|| eyre::eyre!("Latest block not available at {}", self.rpc_url))?;
 
-        provider
+        Ok(FeeContext {
+            block_number: block.header.number.unwrap_or_default(),
+            gas_price,
+            base_fee: block.header.base_fee_per_gas,
+        })
+    }
+
+    // Pins all further reads to the finalized block (falling back to the latest one, if the
+    // node doesn't support the finalized tag), or to the latest block.
+    pub async fn pin_block(&mut self, finalized: bool) -> eyre::Result<u64> {
+        let provider = self.get_provider();
+        let finalized_block = if finalized {
+            provider
+                .get_block_by_number(BlockNumberOrTag::Finalized, false)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|block| block.header.number)
+        } else {
+            None
+        };
+        let block = match finalized_block {
+            Some(block) => block,
+            None => provider.get_block_number().await?,
+        };
+        self.pinned_block = Some(block);
+        self.latest_block = self.latest_block.max(block);
+        self.provider = OnceLock::new();
+        self.archive_provider = OnceLock::new();
+        Ok(block)
+    }
+
+    pub fn pinned_block(&self) -> Option<u64> {
+        self.pinned_block
+    }
+
+    // Copy of the sequencer with all the reads done at the given (e.g. historic) block.
+    pub fn with_pinned_block(&self, block: u64) -> Self {
+        Sequencer {
+            pinned_block: Some(block),
+            provider: OnceLock::new(),
+            archive_provider: OnceLock::new(),
+            ..self.clone()
+        }
+    }
+
+    // Fetches the logs from the given (comma separated) urls instead of the main ones.
+    pub fn with_archive_urls(mut self, archive_rpc_urls: &str) -> eyre::Result<Self> {
+        let urls = split_urls(archive_rpc_urls);
+        // Reject unparsable urls now rather than when the first logs are fetched.
+        FailoverTransport::new(&urls)?;
+        self.archive_rpc_urls = urls;
+        self.archive_provider = OnceLock::new();
+        Ok(self)
+    }
+
+    // Provider for fetching logs - the archive one if configured, the main one otherwise.
+    pub fn get_archive_provider(&self) -> SequencerProvider {
+        if self.archive_rpc_urls.is_empty() {
+            return self.get_provider();
+        }
+        self.archive_provider
+            .get_or_init(|| self.build_provider(&self.archive_rpc_urls))
+            .clone()
+    }
+
+    pub fn get_provider(&self) -> SequencerProvider {
+        self.provider
+            .get_or_init(|| self.build_provider(&self.rpc_urls))
+            .clone()
+    }
+
+    fn build_provider(&self, urls: &[String]) -> SequencerProvider {
+        let transport = FailoverTransport::new(urls)
+            .unwrap()
+            .with_pinned_block(self.pinned_block)
+            .with_batching(crate::transport::batch_requests());
+        RootProvider::new(RpcClient::new(transport, false))
     }
 }
 
-fn is_port_active(address: &str) -> bool {
-    if address.starts_with("https:/") {
+fn is_port_active(url: &Url) -> bool {
+    if url.scheme() == "https" {
         // Assume that https urls are always active.
         return true;
     }
 
     let timeout = Duration::from_secs(1);
-    let address = address.strip_prefix("http://").or(Some(address)).unwrap();
-
-    match TcpStream::connect_timeout(&address.parse().unwrap(), timeout) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+    // Host names (e.g. localhost) are resolved - a host that doesn't resolve isn't active.
+    url.socket_addrs(|| None)
+        .unwrap_or_default()
+        .iter()
+        .any(|address| TcpStream::connect_timeout(address, timeout).is_ok())
 }
 
-async fn send_json_request(url: &str, method: &str) -> eyre::Result<Response> {
+// Raw JSON-RPC call, for the zks_* methods that alloy doesn't know about.
+async fn send_json_request(
+    url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> eyre::Result<Response> {
+    crate::transport::check_method(method).map_err(|err| eyre::eyre!(err))?;
     let client = Client::new();
+    crate::transport::record_call(method, "");
 
     let request_body = json!({
         "jsonrpc": "2.0",
         "id": 1,
         "method": method,
-        "params": []
+        "params": params
     });
 
     let response: Response = client
@@ -87,20 +297,70 @@ async fn send_json_request(url: &str, method: &str) -> eyre::Result<Response> {
     Ok(response)
 }
 
+#[derive(Deserialize, Debug)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+// Merkle proof of an L2 -> L1 log, as returned by zks_getL2ToL1LogProof.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct L2ToL1LogProof {
+    pub proof: Vec<B256>,
+    pub id: u32,
+    pub root: B256,
+}
+
+impl Sequencer {
+    // Proof of the `index`-th L2 -> L1 log of the transaction (the first one if not set).
+    // None if the transaction has no such log, or its batch isn't sealed yet.
+    pub async fn get_l2_to_l1_log_proof(
+        &self,
+        tx_hash: B256,
+        index: Option<u64>,
+    ) -> eyre::Result<Option<L2ToL1LogProof>> {
+        let params = match index {
+            Some(index) => json!([tx_hash, index]),
+            None => json!([tx_hash]),
+        };
+        let response = send_json_request(&self.rpc_url, "zks_getL2ToL1LogProof", params).await?;
+        let response: JsonRpcResponse<L2ToL1LogProof> = response.json().await?;
+        if let Some(error) = response.error {
+            eyre::bail!("zks_getL2ToL1LogProof failed: {}", error);
+        }
+        Ok(response.result)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 struct BridgehubResult {
     result: String,
 }
 
 async fn get_bridgehub_address(url: &str) -> eyre::Result<Address> {
-    let response = send_json_request(url, "zks_getBridgehubContract").await?;
+    let response = send_json_request(url, "zks_getBridgehubContract", json!([])).await?;
     let response_json: BridgehubResult = response.json().await?;
     Ok(Address::from_hex(response_json.result)?)
 }
 
+// Nodes disagree on the shape of the result: `0x` prefixed hex (the spec), but local
+// era-test-node setups were seen returning decimal strings or plain numbers.
 #[derive(Deserialize, Debug)]
 struct L1ChainIdResult {
-    result: String,
+    result: serde_json::Value,
+}
+
+fn parse_l1_chain_id(result: &serde_json::Value) -> eyre::Result<u64> {
+    match result {
+        serde_json::Value::String(value) => match value.strip_prefix("0x") {
+            Some(hex) => Ok(u64::from_str_radix(hex, 16)?),
+            None => Ok(value.parse()?),
+        },
+        serde_json::Value::Number(value) => value
+            .as_u64()
+            .ok_or_else(|| eyre::eyre!("L1 chain id {} doesn't fit into u64", value)),
+        other => eyre::bail!("Unexpected zks_L1ChainId result: {}", other),
+    }
 }
 
 async fn get_l1_chain_id(url: &str) -> eyre::Result<u64> {
@@ -111,46 +371,201 @@ async fn get_l1_chain_id(url: &str) -> eyre::Result<u64> {
         return Ok(31337);
     }
 
-    let response = send_json_request(url, "zks_L1ChainId").await?;
+    let response = send_json_request(url, "zks_L1ChainId", json!([])).await?;
     let response_json: L1ChainIdResult = response.json().await?;
-    let trimmed_hex = response_json.result.trim_start_matches("0x");
-    Ok(u64::from_str_radix(trimmed_hex, 16)?)
+    parse_l1_chain_id(&response_json.result)
+}
+
+fn split_urls(rpc_urls: &str) -> Vec<String> {
+    rpc_urls
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect()
 }
 
 // Detects the sequencer that is operating a given host / port.
 // Can detect both L1 and L2.
-pub async fn detect_sequencer(rpc_url: &str) -> eyre::Result<Sequencer> {
-    if !is_port_active(rpc_url) {
-        eyre::bail!("Port not active: {}", rpc_url);
+// `rpc_urls` is a comma separated list of urls for the same chain - they are tried in order
+// until one responds, and the remaining ones are kept as fallbacks.
+pub async fn detect_sequencer(rpc_urls: &str) -> Result<Sequencer, SequencerError> {
+    let cache = CACHE_DETECTION.load(Ordering::Relaxed);
+    if cache {
+        if let Some(sequencer) = DETECTED.lock().unwrap().get(rpc_urls) {
+            return Ok(sequencer.clone());
+        }
+    }
+
+    let sequencer = detect_sequencer_uncached(rpc_urls).await?;
+    if cache {
+        DETECTED
+            .lock()
+            .unwrap()
+            .insert(rpc_urls.to_string(), sequencer.clone());
+    }
+    Ok(sequencer)
+}
+
+async fn detect_sequencer_uncached(rpc_urls: &str) -> Result<Sequencer, SequencerError> {
+    let urls = split_urls(rpc_urls);
+
+    let mut last_error = SequencerError::InvalidUrl {
+        rpc_url: rpc_urls.to_string(),
+        reason: "no urls provided".to_string(),
+    };
+    for (index, url) in urls.iter().enumerate() {
+        match detect_single_sequencer(url).await {
+            Ok(mut sequencer) => {
+                let mut fallbacks = urls.clone();
+                fallbacks.remove(index);
+                sequencer.rpc_urls.extend(fallbacks);
+                return Ok(sequencer);
+            }
+            Err(err) => {
+                if urls.len() > 1 {
+                    progress!("Failed to detect sequencer at {}: {}", url, err);
+                }
+                last_error = err;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+async fn detect_single_sequencer(rpc_url: &str) -> Result<Sequencer, SequencerError> {
+    let url: Url = rpc_url.parse().map_err(|err| SequencerError::InvalidUrl {
+        rpc_url: rpc_url.to_string(),
+        reason: format!("{}", err),
+    })?;
+    if !is_port_active(&url) {
+        return Err(SequencerError::PortNotActive {
+            rpc_url: rpc_url.to_string(),
+        });
     }
+    let rpc_failed = |err: &dyn Display| SequencerError::RpcFailed {
+        rpc_url: rpc_url.to_string(),
+        reason: err.to_string(),
+    };
 
-    // Create a provider with the HTTP transport using the `reqwest` crate.
-    let provider: alloy::providers::RootProvider<
-        alloy::transports::http::Http<alloy::transports::http::Client>,
-    > = ProviderBuilder::new().on_http(rpc_url.parse()?);
+    // Through the failover transport (with a single url), so that the detection requests are
+    // subject to the method policy and counted like all the others.
+    let transport = FailoverTransport::new(&[rpc_url.to_string()]).map_err(|err| {
+        SequencerError::InvalidUrl {
+            rpc_url: rpc_url.to_string(),
+            reason: err.to_string(),
+        }
+    })?;
+    let provider: SequencerProvider = RootProvider::new(RpcClient::new(transport, false));
 
-    let chain_id = provider.get_chain_id().await?;
-    println!("Detected chain ID: {}", chain_id);
-    let latest_block = provider.get_block_number().await?;
+    let chain_id =
+        provider
+            .get_chain_id()
+            .await
+            .map_err(|err| SequencerError::ChainIdFetchFailed {
+                rpc_url: rpc_url.to_string(),
+                reason: err.to_string(),
+            })?;
+    progress!("Detected chain ID: {}", chain_id);
+    let latest_block = provider
+        .get_block_number()
+        .await
+        .map_err(|err| rpc_failed(&err))?;
 
     // Now let's see if this is an 'L2' or 'L1'.
     let sequencer_type = match get_bridgehub_address(rpc_url).await {
         Ok(bridgehub_address) => SequencerType::L2(L2SequencerInfo {
             bridgehub_address,
-            l1_chain_id: get_l1_chain_id(rpc_url).await?,
+            l1_chain_id: get_l1_chain_id(rpc_url)
+                .await
+                .map_err(|err| rpc_failed(&err))?,
         }),
-        Err(_) => SequencerType::L1,
+        Err(err) => {
+            progress!(
+                "No zks_getBridgehubContract at {} ({}) - treating it as L1",
+                rpc_url,
+                err
+            );
+            SequencerType::L1
+        }
     };
 
-    println!(
+    progress!(
         "Detected sequencer type: {:?} at {}",
-        sequencer_type, rpc_url
+        sequencer_type,
+        rpc_url
     );
 
     Ok(Sequencer {
         rpc_url: rpc_url.to_string(),
+        rpc_urls: vec![rpc_url.to_string()],
+        archive_rpc_urls: Vec::new(),
         chain_id,
         latest_block,
+        pinned_block: None,
         sequencer_type,
+        provider: OnceLock::new(),
+        archive_provider: OnceLock::new(),
     })
 }
+
+// Same as detect_sequencer, but fails if the endpoint turns out to be a plain L1.
+pub async fn detect_l2_sequencer(rpc_urls: &str) -> Result<Sequencer, SequencerError> {
+    let sequencer = detect_sequencer(rpc_urls).await?;
+    match sequencer.sequencer_type {
+        SequencerType::L2(_) => Ok(sequencer),
+        SequencerType::L1 => Err(SequencerError::NotAZkSyncSequencer {
+            rpc_url: sequencer.rpc_url,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_host_names_when_checking_the_port() {
+        // Nothing listens on port 1 - the point is that the host name doesn't panic.
+        assert!(!is_port_active(&"http://localhost:1".parse().unwrap()));
+        assert!(!is_port_active(&"http://127.0.0.1:1".parse().unwrap()));
+        assert!(is_port_active(&"https://example.invalid".parse().unwrap()));
+    }
+
+    #[test]
+    fn pinning_a_block_resets_the_cached_providers() {
+        let sequencer = Sequencer {
+            rpc_url: "http://localhost:1".to_string(),
+            rpc_urls: vec!["http://localhost:1".to_string()],
+            archive_rpc_urls: Vec::new(),
+            chain_id: 500,
+            latest_block: 100,
+            pinned_block: None,
+            sequencer_type: SequencerType::L1,
+            provider: OnceLock::new(),
+            archive_provider: OnceLock::new(),
+        };
+        sequencer.get_provider();
+
+        let historic = sequencer.with_pinned_block(10);
+
+        assert_eq!(historic.pinned_block(), Some(10));
+        assert!(historic.provider.get().is_none());
+        assert!(sequencer.provider.get().is_some());
+    }
+
+    #[tokio::test]
+    async fn invalid_urls_are_reported() {
+        let result = detect_single_sequencer("not a url").await;
+        assert!(matches!(result, Err(SequencerError::InvalidUrl { .. })));
+    }
+
+    #[test]
+    fn parses_hex_and_decimal_l1_chain_ids() {
+        assert_eq!(parse_l1_chain_id(&json!("0x7a69")).unwrap(), 31337);
+        assert_eq!(parse_l1_chain_id(&json!("31337")).unwrap(), 31337);
+        assert_eq!(parse_l1_chain_id(&json!(31337)).unwrap(), 31337);
+        assert!(parse_l1_chain_id(&json!("0xzz")).is_err());
+        assert!(parse_l1_chain_id(&json!(null)).is_err());
+    }
+}
@@ -3,13 +3,18 @@ use std::fmt::Display;
 use alloy::primitives::{Address, U256};
 use alloy::primitives::{FixedBytes, B256};
 use alloy::sol;
+use alloy::sol_types::SolEvent;
 use colored::Colorize;
 
 use crate::addresses::add_address_name;
+use crate::error::DebuggerError;
 use crate::priority_transactions::{
-    compute_merkle_tree, fetch_all_priority_transactions, PriorityTransaction,
+    check_merkle_tree_size, compute_merkle_tree, fetch_priority_transactions,
+    FetchedPriorityTransactions, PriorityScanRange, PriorityTransaction, PriorityTreeHashing,
 };
-use crate::sequencer::Sequencer;
+use crate::sequencer::{Sequencer, SequencerProvider};
+use crate::stm::IChainTypeManager;
+use crate::utils::{format_implementation, get_all_events, get_proxy_implementation, ContractCode};
 use serde::Serialize;
 
 fn format_address(value: Address) -> String {
@@ -24,6 +29,123 @@ fn format_b256(value: B256) -> String {
     format!("{:#x}", value)
 }
 
+// Data availability mode of the chain, from getPubdataPricingMode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DaMode {
+    Rollup,
+    Validium,
+    // The hyperchain doesn't expose the getter (or returned something we don't know).
+    Unknown,
+}
+
+impl Display for DaMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            DaMode::Rollup => "rollup",
+            DaMode::Validium => "validium",
+            DaMode::Unknown => "unknown",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// Set of getters that the hyperchain exposes, selected by its protocol version.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContractLayout {
+    // Before v24 - only the core getters are read, the rest may not exist.
+    Legacy,
+    // v24 and v25 - no settlement layer and no priority tree yet.
+    PreGateway,
+    // v26 up to LATEST_KNOWN_VERSION.
+    Gateway,
+    // Newer than this tool knows - read as Gateway, some of the getters may have changed.
+    Newer,
+}
+
+impl ContractLayout {
+    pub const FIRST_SUPPORTED_VERSION: u32 = 24;
+    pub const FIRST_GATEWAY_VERSION: u32 = 26;
+    pub const LATEST_KNOWN_VERSION: u32 = 28;
+
+    pub fn for_protocol_version(protocol_version: (u32, u32, u32)) -> Self {
+        match protocol_version {
+            (0, minor, _) if minor < Self::FIRST_SUPPORTED_VERSION => ContractLayout::Legacy,
+            (0, minor, _) if minor < Self::FIRST_GATEWAY_VERSION => ContractLayout::PreGateway,
+            (0, minor, _) if minor <= Self::LATEST_KNOWN_VERSION => ContractLayout::Gateway,
+            _ => ContractLayout::Newer,
+        }
+    }
+
+    fn has_settlement_layer(&self) -> bool {
+        matches!(self, ContractLayout::Gateway | ContractLayout::Newer)
+    }
+
+    fn has_priority_tree(&self) -> bool {
+        matches!(self, ContractLayout::Gateway | ContractLayout::Newer)
+    }
+
+    fn has_da_validators(&self) -> bool {
+        !matches!(self, ContractLayout::Legacy)
+    }
+
+    // Why the chain is only partially supported, None if the layout is fully known.
+    pub fn warning(&self, protocol_version: (u32, u32, u32)) -> Option<String> {
+        let (major, minor, patch) = protocol_version;
+        match self {
+            ContractLayout::Legacy => Some(format!(
+                "Chain uses protocol v{} which this tool supports partially (v{}+ is fully \
+                 supported): only the core getters are read",
+                minor,
+                Self::FIRST_SUPPORTED_VERSION
+            )),
+            ContractLayout::Newer => Some(format!(
+                "Chain uses protocol {}.{}.{} which is newer than this tool knows (up to v{}): \
+                 read with the v{} layout",
+                major,
+                minor,
+                patch,
+                Self::LATEST_KNOWN_VERSION,
+                Self::LATEST_KNOWN_VERSION
+            )),
+            ContractLayout::PreGateway | ContractLayout::Gateway => None,
+        }
+    }
+}
+
+impl Display for ContractLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            ContractLayout::Legacy => "legacy",
+            ContractLayout::PreGateway => "pre-gateway",
+            ContractLayout::Gateway => "gateway",
+            ContractLayout::Newer => "newer",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// Protocol version of the hyperchain. Chains that predate the semver getter return the packed
+// version (or, before that, just the minor version) from getProtocolVersion.
+async fn fetch_protocol_version(
+    provider: &SequencerProvider,
+    hyperchain: Address,
+) -> eyre::Result<(u32, u32, u32)> {
+    let contract = IHyperchain::new(hyperchain, provider);
+    if let Ok(version) = contract.getSemverProtocolVersion().call().await {
+        return Ok((version._0, version._1, version._2));
+    }
+    let packed = contract.getProtocolVersion().call().await?._0;
+    Ok(legacy_protocol_version(packed))
+}
+
+fn legacy_protocol_version(packed: U256) -> (u32, u32, u32) {
+    if packed < U256::from(1u64 << 32) {
+        (0, packed.to::<u32>(), 0)
+    } else {
+        unpack_semver(packed)
+    }
+}
+
 #[derive(Debug)]
 pub struct StateTransition {
     verifier: Address,
@@ -33,21 +155,78 @@ pub struct StateTransition {
     bootloader_hash: FixedBytes<32>,
     default_account_hash: FixedBytes<32>,
     protocol_version: (u32, u32, u32),
+    layout: ContractLayout,
     system_upgrade_tx_hash: FixedBytes<32>,
     admin: Address,
     chain_id: U256,
     settlement_layer: Address,
+    da_mode: DaMode,
+    // L1 and L2 DA validators, None if the hyperchain doesn't expose getDAValidatorPair.
+    da_validators: Option<(Address, Address)>,
 
     unprocessed_queue_size: U256,
     total_queue_size: U256,
-    priority_tree_root: B256,
+    // None on chains that predate the priority tree (before v26).
+    priority_tree_root: Option<B256>,
 
     hyperchain: Address,
+    implementation: Option<Address>,
+    code: ContractCode,
+}
+
+pub struct PriorityTreeVerification {
+    pub onchain_root: B256,
+    pub computed_root: B256,
+}
+
+impl PriorityTreeVerification {
+    pub fn is_valid(&self) -> bool {
+        self.onchain_root == self.computed_root
+    }
+}
+
+// How much of the priority queue a scan of the priority transactions covered.
+#[derive(Debug, Serialize)]
+pub struct PriorityScan {
+    pub fetched: usize,
+    pub onchain_total: String,
+    pub min_index: Option<u64>,
+    pub max_index: Option<u64>,
+    // All the transactions from 0 to onchain_total - 1 were fetched.
+    pub complete: bool,
+    // Logs in the scanned range that failed to decode (not part of `fetched`).
+    pub skipped_logs: usize,
+}
+
+impl PriorityScan {
+    pub fn new(indices: &[u64], onchain_total: U256) -> Self {
+        let fetched = indices.len();
+        let min_index = indices.iter().min().copied();
+        let max_index = indices.iter().max().copied();
+        let reached_last = match max_index {
+            Some(highest) => U256::from(highest) + U256::from(1) >= onchain_total,
+            None => onchain_total.is_zero(),
+        };
+        let complete = reached_last
+            && U256::from(fetched) >= onchain_total
+            && indices.iter().all(|index| (*index as usize) < fetched);
+
+        Self {
+            fetched,
+            onchain_total: onchain_total.to_string(),
+            min_index,
+            max_index,
+            complete,
+            skipped_logs: 0,
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct QueueReport {
     pub unprocessed: String,
+    // total - unprocessed.
+    pub processed: String,
     pub total: String,
 }
 
@@ -55,18 +234,100 @@ pub struct QueueReport {
 pub struct StateTransitionReport {
     pub chain_id: String,
     pub hyperchain: String,
+    pub implementation: Option<String>,
+    pub contract_code_hash: String,
+    pub contract_code_size: usize,
     pub verifier: String,
+    // With --expected-verifiers: the name of the verifier, None if it isn't a known one.
+    pub verifier_name: Option<String>,
     pub total_batches_executed: String,
     pub total_batches_verified: String,
     pub total_batches_committed: String,
+    // committed - verified and verified - executed batches.
+    pub commit_to_verify_lag: String,
+    pub verify_to_execute_lag: String,
     pub bootloader_hash: String,
     pub default_account_hash: String,
     pub protocol_version: (u32, u32, u32),
+    pub contract_layout: String,
+    // Set when the protocol version is only partially supported.
+    pub layout_warning: Option<String>,
     pub system_upgrade_tx_hash: String,
     pub admin: String,
     pub settlement_layer: String,
+    pub da_mode: String,
+    // "none" when the hyperchain doesn't expose getDAValidatorPair.
+    pub l1_da_validator: String,
+    pub l2_da_validator: String,
     pub queue: QueueReport,
-    pub priority_tree_root: String,
+    // null on chains that predate the priority tree.
+    pub priority_tree_root: Option<String>,
+    // Set when the chain's CTM already has a newer protocol version than the chain.
+    pub pending_upgrade: Option<PendingUpgrade>,
+}
+
+// Newer protocol version that the chain still has to upgrade to.
+#[derive(Debug, Serialize)]
+pub struct PendingUpgrade {
+    pub target_version: String,
+    // Until when the current version of the chain is supported, None if there is no deadline.
+    pub deadline_unix: Option<u64>,
+    pub past_deadline: bool,
+}
+
+impl Display for PendingUpgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let deadline = match self.deadline_unix {
+            Some(deadline) => format!("deadline {}", deadline),
+            None => "no deadline".to_string(),
+        };
+        let message = format!("Pending upgrade to {} ({})", self.target_version, deadline);
+        if self.past_deadline {
+            write!(f, "{} {}", message.red(), "PAST DEADLINE".red().bold())
+        } else {
+            write!(f, "{}", message.yellow())
+        }
+    }
+}
+
+// How far a batch got, based on the batch counters of the chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchStatus {
+    Executed,
+    Verified,
+    Committed,
+    NotCommitted,
+}
+
+impl Display for BatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            BatchStatus::Executed => "executed".green(),
+            BatchStatus::Verified => "verified".yellow(),
+            BatchStatus::Committed => "committed".yellow(),
+            BatchStatus::NotCommitted => "not committed".red(),
+        };
+        write!(f, "{}", label)
+    }
+}
+
+pub struct BatchInfo {
+    pub number: u64,
+    pub status: BatchStatus,
+    // Hash of the stored batch info, zero for batches that are not committed (or reverted).
+    pub stored_hash: B256,
+    // From the BlockCommit event - None if it isn't within the recently scanned blocks.
+    pub commitment: Option<B256>,
+}
+
+// Protocol versions are packed on-chain as `major << 64 | minor << 32 | patch`.
+fn pack_semver((major, minor, patch): (u32, u32, u32)) -> U256 {
+    (U256::from(major) << 64) | (U256::from(minor) << 32) | U256::from(patch)
+}
+
+fn unpack_semver(packed: U256) -> (u32, u32, u32) {
+    let part = |shift: usize| (packed >> shift).wrapping_to::<u32>();
+    (part(64), part(32), part(0))
 }
 
 sol! {
@@ -78,6 +339,7 @@ sol! {
         function getTotalBatchesVerified() external view returns (uint256);
         function getTotalBatchesExecuted() external view returns (uint256);
         function getSemverProtocolVersion() external view returns (uint32, uint32, uint32);
+        function getProtocolVersion() external view returns (uint256);
 
         function getL2BootloaderBytecodeHash() external view returns (bytes32);
         function getL2DefaultAccountBytecodeHash() external view returns (bytes32);
@@ -88,10 +350,22 @@ sol! {
         function getPriorityQueueSize() external view returns (uint256);
         function getTotalPriorityTxs() external view returns (uint256);
         function getPriorityTreeRoot() external view returns (bytes32);
+        function getPubdataPricingMode() external view returns (uint8);
+        function getChainTypeManager() external view returns (address);
+        function getDAValidatorPair() external view returns (address, address);
+        function storedBatchHash(uint256 _batchNumber) external view returns (bytes32);
+
+        event BlockCommit(uint256 indexed batchNumber, bytes32 indexed batchHash, bytes32 indexed commitment);
 
     }
 }
 
+fn format_da_validator(validator: Option<Address>) -> String {
+    validator
+        .map(format_address)
+        .unwrap_or_else(|| "none".to_string())
+}
+
 fn mark_red_if_not_empty<T: std::fmt::Display + core::cmp::PartialEq>(
     address: T,
     empty: T,
@@ -99,7 +373,21 @@ fn mark_red_if_not_empty<T: std::fmt::Display + core::cmp::PartialEq>(
     if address == empty {
         return address.to_string();
     }
-    return format!("{}", address).red().to_string();
+    format!("{}", address).red().to_string()
+}
+
+// Address of the settlement layer's hyperchain, zero if the chain settles on this layer.
+// Chains that predate the gateway have no getSettlementLayer - they always settle on L1.
+pub async fn get_settlement_layer(
+    provider: &SequencerProvider,
+    hyperchain: Address,
+) -> eyre::Result<Address> {
+    let protocol_version = fetch_protocol_version(provider, hyperchain).await?;
+    if !ContractLayout::for_protocol_version(protocol_version).has_settlement_layer() {
+        return Ok(Address::ZERO);
+    }
+    let contract = IHyperchain::new(hyperchain, provider);
+    Ok(contract.getSettlementLayer().call().await?._0)
 }
 
 impl Display for StateTransition {
@@ -109,19 +397,36 @@ impl Display for StateTransition {
 }
 
 impl StateTransition {
+    // Detects the protocol version first, and reads the getters of the matching layout.
     pub async fn new(
-        provider: &alloy::providers::RootProvider<
-            alloy::transports::http::Http<alloy::transports::http::Client>,
-        >,
+        provider: &SequencerProvider,
+        hyperchain: Address,
+    ) -> eyre::Result<StateTransition> {
+        let protocol_version = fetch_protocol_version(provider, hyperchain).await?;
+        let layout = ContractLayout::for_protocol_version(protocol_version);
+
+        Self::read(provider, hyperchain, protocol_version, layout)
+            .await
+            .map_err(|err| match layout.warning(protocol_version) {
+                Some(warning) => err.wrap_err(warning),
+                None => err,
+            })
+    }
+
+    async fn read(
+        provider: &SequencerProvider,
         hyperchain: Address,
+        protocol_version: (u32, u32, u32),
+        layout: ContractLayout,
     ) -> eyre::Result<StateTransition> {
+        let implementation = get_proxy_implementation(provider, hyperchain).await?;
+        let code = ContractCode::fetch(provider, hyperchain).await?;
         let contract = IHyperchain::new(hyperchain, provider);
 
         let verifier = contract.getVerifier().call().await?._0;
         let total_batches_committed = contract.getTotalBatchesCommitted().call().await?._0;
         let total_batches_verified = contract.getTotalBatchesVerified().call().await?._0;
         let total_batches_executed = contract.getTotalBatchesExecuted().call().await?._0;
-        let protocol_version = contract.getSemverProtocolVersion().call().await?;
 
         let admin = contract.getAdmin().call().await?._0;
 
@@ -136,12 +441,36 @@ impl StateTransition {
         let chain_id = contract.getChainId().call().await?._0;
 
         add_address_name(admin, format!("Admin {}", chain_id));
-        let settlement_layer = contract.getSettlementLayer().call().await?._0;
+        // Before the gateway, chains always settled on L1.
+        let settlement_layer = if layout.has_settlement_layer() {
+            contract.getSettlementLayer().call().await?._0
+        } else {
+            Address::ZERO
+        };
+        let da_mode = match contract.getPubdataPricingMode().call().await {
+            Ok(mode) if mode._0 == 0 => DaMode::Rollup,
+            Ok(mode) if mode._0 == 1 => DaMode::Validium,
+            _ => DaMode::Unknown,
+        };
+        let da_validators = if layout.has_da_validators() {
+            contract
+                .getDAValidatorPair()
+                .call()
+                .await
+                .ok()
+                .map(|pair| (pair._0, pair._1))
+        } else {
+            None
+        };
 
         let unprocessed_queue_size = contract.getPriorityQueueSize().call().await?._0;
         let total_queue_size = contract.getTotalPriorityTxs().call().await?._0;
 
-        let priority_tree_root = contract.getPriorityTreeRoot().call().await?._0;
+        let priority_tree_root = if layout.has_priority_tree() {
+            Some(contract.getPriorityTreeRoot().call().await?._0)
+        } else {
+            None
+        };
 
         Ok(StateTransition {
             verifier,
@@ -150,41 +479,184 @@ impl StateTransition {
             total_batches_committed,
             bootloader_hash,
             default_account_hash,
-            protocol_version: (
-                protocol_version._0,
-                protocol_version._1,
-                protocol_version._2,
-            ),
+            protocol_version,
+            layout,
             system_upgrade_tx_hash,
             admin,
             chain_id,
             settlement_layer,
+            da_mode,
+            da_validators,
             unprocessed_queue_size,
             total_queue_size,
             priority_tree_root,
             hyperchain,
+            implementation,
+            code,
         })
     }
 
+    pub fn protocol_version(&self) -> (u32, u32, u32) {
+        self.protocol_version
+    }
+
+    pub fn layout_warning(&self) -> Option<String> {
+        self.layout.warning(self.protocol_version)
+    }
+
+    pub fn admin(&self) -> Address {
+        self.admin
+    }
+
+    pub fn verifier(&self) -> Address {
+        self.verifier
+    }
+
+    // Checks the chain's CTM for a newer protocol version than the chain's, and the deadline
+    // until which the chain's current version remains supported.
+    pub async fn pending_upgrade(
+        &self,
+        provider: &SequencerProvider,
+        now_unix: u64,
+    ) -> eyre::Result<Option<PendingUpgrade>> {
+        let ctm = IHyperchain::new(self.hyperchain, provider)
+            .getChainTypeManager()
+            .call()
+            .await?
+            ._0;
+        let ctm = IChainTypeManager::new(ctm, provider);
+        let latest = ctm.protocolVersion().call().await?._0;
+        let current = pack_semver(self.protocol_version);
+        if latest <= current {
+            return Ok(None);
+        }
+
+        let deadline = ctm.protocolVersionDeadline(current).call().await?._0;
+        // type(uint256).max marks a version without a deadline.
+        let deadline_unix =
+            (deadline != U256::MAX).then(|| u64::try_from(deadline).unwrap_or(u64::MAX));
+        let (major, minor, patch) = unpack_semver(latest);
+        Ok(Some(PendingUpgrade {
+            target_version: format!("{}.{}.{}", major, minor, patch),
+            deadline_unix,
+            past_deadline: deadline_unix.is_some_and(|deadline| deadline < now_unix),
+        }))
+    }
+
+    pub fn batch_status(&self, batch: u64) -> BatchStatus {
+        let batch = U256::from(batch);
+        if batch <= self.total_batches_executed {
+            BatchStatus::Executed
+        } else if batch <= self.total_batches_verified {
+            BatchStatus::Verified
+        } else if batch <= self.total_batches_committed {
+            BatchStatus::Committed
+        } else {
+            BatchStatus::NotCommitted
+        }
+    }
+
+    // Stored hash and status of a single batch, with its commitment if the BlockCommit event
+    // is within the recent blocks.
+    pub async fn batch_info(&self, sequencer: &Sequencer, batch: u64) -> eyre::Result<BatchInfo> {
+        let contract = IHyperchain::new(self.hyperchain, sequencer.get_provider());
+        let stored_hash = contract.storedBatchHash(U256::from(batch)).call().await?._0;
+
+        let events = get_all_events(
+            sequencer,
+            self.hyperchain,
+            IHyperchain::BlockCommit::SIGNATURE_HASH,
+            5000, // 5k block limit
+        )
+        .await?;
+        // A reverted batch can be committed again - the last commit is the current one.
+        let commitment = events
+            .iter()
+            .filter_map(|log| log.log_decode::<IHyperchain::BlockCommit>().ok())
+            .map(|log| log.inner.data)
+            .filter(|event| event.batchNumber == U256::from(batch))
+            .last()
+            .map(|event| event.commitment);
+
+        Ok(BatchInfo {
+            number: batch,
+            status: self.batch_status(batch),
+            stored_hash,
+            commitment,
+        })
+    }
+
+    // Batches committed but not verified yet (saturating, like the queue sizes).
+    pub fn commit_to_verify_lag(&self) -> U256 {
+        self.total_batches_committed
+            .saturating_sub(self.total_batches_verified)
+    }
+
+    // Batches verified but not executed yet.
+    pub fn verify_to_execute_lag(&self) -> U256 {
+        self.total_batches_verified
+            .saturating_sub(self.total_batches_executed)
+    }
+
+    // Batches committed but not executed yet.
+    pub fn batch_lag(&self) -> U256 {
+        self.total_batches_committed
+            .saturating_sub(self.total_batches_executed)
+    }
+
+    pub fn unprocessed_queue_size(&self) -> U256 {
+        self.unprocessed_queue_size
+    }
+
+    // Number of priority transactions already processed. Saturates at zero, in case the
+    // two getters are ever inconsistent.
+    pub fn processed_queue_size(&self) -> U256 {
+        self.total_queue_size
+            .saturating_sub(self.unprocessed_queue_size)
+    }
+
+    // Priority transactions are processed in order, so everything below the processed count is.
+    pub fn is_priority_processed(&self, index: u64) -> bool {
+        U256::from(index) < self.processed_queue_size()
+    }
+
+    // Chain has migrated to a different settlement layer, and this contract is only a leftover.
+    pub fn is_passive(&self) -> bool {
+        self.settlement_layer != Address::ZERO
+    }
+
     pub fn to_report(&self) -> StateTransitionReport {
         StateTransitionReport {
             chain_id: self.chain_id.to_string(),
             hyperchain: format_address(self.hyperchain),
+            implementation: self.implementation.map(format_address),
+            contract_code_hash: format_b256(self.code.hash),
+            contract_code_size: self.code.size,
             verifier: format_address(self.verifier),
+            verifier_name: None,
             total_batches_executed: self.total_batches_executed.to_string(),
             total_batches_verified: self.total_batches_verified.to_string(),
             total_batches_committed: self.total_batches_committed.to_string(),
+            commit_to_verify_lag: self.commit_to_verify_lag().to_string(),
+            verify_to_execute_lag: self.verify_to_execute_lag().to_string(),
             bootloader_hash: format_fixed_bytes(self.bootloader_hash),
             default_account_hash: format_fixed_bytes(self.default_account_hash),
             protocol_version: self.protocol_version,
+            contract_layout: self.layout.to_string(),
+            layout_warning: self.layout_warning(),
             system_upgrade_tx_hash: format_fixed_bytes(self.system_upgrade_tx_hash),
             admin: format_address(self.admin),
             settlement_layer: format_address(self.settlement_layer),
+            da_mode: self.da_mode.to_string(),
+            l1_da_validator: format_da_validator(self.da_validators.map(|pair| pair.0)),
+            l2_da_validator: format_da_validator(self.da_validators.map(|pair| pair.1)),
             queue: QueueReport {
                 unprocessed: self.unprocessed_queue_size.to_string(),
+                processed: self.processed_queue_size().to_string(),
                 total: self.total_queue_size.to_string(),
             },
-            priority_tree_root: format_b256(self.priority_tree_root),
+            priority_tree_root: self.priority_tree_root.map(format_b256),
+            pending_upgrade: None,
         }
     }
 
@@ -196,6 +668,9 @@ impl StateTransition {
             "{}  Protocol version: {}.{}.{}",
             pad, self.protocol_version.0, self.protocol_version.1, self.protocol_version.2
         )?;
+        if let Some(warning) = self.layout_warning() {
+            writeln!(f, "{}  {} {}", pad, "[WARN]".yellow(), warning)?;
+        }
         writeln!(
             f,
             "{}  Batches (C,V,E):  {} {} {}",
@@ -204,6 +679,13 @@ impl StateTransition {
             self.total_batches_verified,
             self.total_batches_executed
         )?;
+        writeln!(
+            f,
+            "{}  Lag (C-V, V-E):   {} {}",
+            pad,
+            self.commit_to_verify_lag(),
+            self.verify_to_execute_lag()
+        )?;
 
         writeln!(
             f,
@@ -214,23 +696,33 @@ impl StateTransition {
         writeln!(
             f,
             "{}  AA hash:          {}",
+            pad, self.default_account_hash
+        )?;
+        writeln!(
+            f,
+            "{}  Implementation:   {}",
             pad,
-            self.default_account_hash.to_string()
+            format_implementation(self.implementation)
         )?;
+        writeln!(f, "{}  Code:             {}", pad, self.code)?;
         writeln!(f, "{}  Verifier:         {}", pad, self.verifier)?;
         writeln!(f, "{}  Admin:            {}", pad, self.admin)?;
+        writeln!(f, "{}  Bootloader hash:  {}", pad, self.bootloader_hash)?;
+
         writeln!(
             f,
-            "{}  Bootloader hash:  {}",
+            "{}  Settlement layer: {}",
             pad,
-            self.bootloader_hash.to_string()
+            mark_red_if_not_empty(self.settlement_layer, Address::ZERO)
         )?;
 
+        writeln!(f, "{}  DA mode:          {}", pad, self.da_mode)?;
         writeln!(
             f,
-            "{}  Settlement layer: {}",
+            "{}  DA validators:    {} (L1) {} (L2)",
             pad,
-            mark_red_if_not_empty(self.settlement_layer, Address::ZERO)
+            format_da_validator(self.da_validators.map(|pair| pair.0)),
+            format_da_validator(self.da_validators.map(|pair| pair.1))
         )?;
 
         writeln!(
@@ -238,6 +730,13 @@ impl StateTransition {
             "{}  Queue unprocessed / total: {} / {}",
             pad, self.unprocessed_queue_size, self.total_queue_size
         )?;
+        writeln!(
+            f,
+            "{}  Queue processed / total:   {} / {}",
+            pad,
+            self.processed_queue_size(),
+            self.total_queue_size
+        )?;
 
         Ok(())
     }
@@ -245,20 +744,417 @@ impl StateTransition {
     pub async fn get_priority_transactions(
         &self,
         sequencer: &Sequencer,
-    ) -> eyre::Result<Vec<PriorityTransaction>> {
-        fetch_all_priority_transactions(sequencer, self.hyperchain).await
+        range: PriorityScanRange,
+    ) -> eyre::Result<FetchedPriorityTransactions> {
+        fetch_priority_transactions(sequencer, self.hyperchain, range).await
     }
 
-    pub async fn verify_priority_root_hash(&self, sequencer: &Sequencer) -> eyre::Result<()> {
-        let txs = self.get_priority_transactions(sequencer).await?;
-        if compute_merkle_tree(&txs) != self.priority_tree_root {
-            eyre::bail!(
-                "Priority tree root hash invalid: {} vs {}",
-                self.priority_tree_root,
-                compute_merkle_tree(&txs)
-            )
+    // Which part of the priority queue the fetched transactions cover.
+    pub fn priority_scan(&self, txs: &[PriorityTransaction]) -> PriorityScan {
+        let indices: Vec<u64> = txs.iter().map(|tx| tx.index).collect();
+        PriorityScan::new(&indices, self.total_queue_size)
+    }
+
+    // Recomputes the priority tree from the fetched NewPriorityRequest events and compares it
+    // with the on-chain root. Fails with IncompletePriorityScan if the scanned block range didn't
+    // cover all the transactions, as the computed root would be meaningless in that case.
+    pub async fn verify_priority_root_hash(
+        &self,
+        sequencer: &Sequencer,
+    ) -> eyre::Result<PriorityTreeVerification> {
+        let hashing = PriorityTreeHashing::for_protocol_version(self.protocol_version);
+        let (Some(hashing), Some(onchain_root)) = (hashing, self.priority_tree_root) else {
+            return Err(DebuggerError::NoPriorityTree {
+                protocol_version: self.protocol_version,
+                first_tree_version: PriorityTreeHashing::FIRST_TREE_VERSION,
+            }
+            .into());
+        };
+        // Fail before scanning the whole history for a tree that can't be computed anyway.
+        check_merkle_tree_size(usize::try_from(self.total_queue_size).unwrap_or(usize::MAX))?;
+        let txs = self
+            .get_priority_transactions(sequencer, PriorityScanRange::FullHistory)
+            .await?
+            .txs;
+        let scan = self.priority_scan(&txs);
+        if !scan.complete {
+            return Err(DebuggerError::IncompletePriorityScan {
+                fetched: scan.fetched,
+                highest_index: scan.max_index,
+                total: self.total_queue_size,
+            }
+            .into());
         }
 
-        Ok(())
+        Ok(PriorityTreeVerification {
+            onchain_root,
+            computed_root: compute_merkle_tree(&txs, hashing),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256, Address, U256};
+    use alloy::sol_types::SolCall;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::MockTransport;
+
+    const HYPERCHAIN: Address = address!("1000000000000000000000000000000000000001");
+
+    fn hyperchain_mock() -> MockTransport {
+        MockTransport::default()
+            .with_response("eth_getStorageAt", json!("0x0"))
+            .with_response("eth_getCode", json!("0x6080"))
+            .with_call::<IHyperchain::getVerifierCall>(
+                IHyperchain::getVerifierCall::abi_encode_returns(&(address!(
+                    "2000000000000000000000000000000000000002"
+                ),)),
+            )
+            .with_call::<IHyperchain::getAdminCall>(IHyperchain::getAdminCall::abi_encode_returns(
+                &(address!("3000000000000000000000000000000000000003"),),
+            ))
+            .with_call::<IHyperchain::getTotalBatchesCommittedCall>(
+                IHyperchain::getTotalBatchesCommittedCall::abi_encode_returns(&(U256::from(12),)),
+            )
+            .with_call::<IHyperchain::getTotalBatchesVerifiedCall>(
+                IHyperchain::getTotalBatchesVerifiedCall::abi_encode_returns(&(U256::from(11),)),
+            )
+            .with_call::<IHyperchain::getTotalBatchesExecutedCall>(
+                IHyperchain::getTotalBatchesExecutedCall::abi_encode_returns(&(U256::from(10),)),
+            )
+            .with_call::<IHyperchain::getSemverProtocolVersionCall>(
+                IHyperchain::getSemverProtocolVersionCall::abi_encode_returns(&(0u32, 26u32, 0u32)),
+            )
+            .with_call::<IHyperchain::getL2BootloaderBytecodeHashCall>(
+                IHyperchain::getL2BootloaderBytecodeHashCall::abi_encode_returns(&(b256!(
+                    "0100000000000000000000000000000000000000000000000000000000000001"
+                ),)),
+            )
+            .with_call::<IHyperchain::getL2DefaultAccountBytecodeHashCall>(
+                IHyperchain::getL2DefaultAccountBytecodeHashCall::abi_encode_returns(&(b256!(
+                    "0100000000000000000000000000000000000000000000000000000000000002"
+                ),)),
+            )
+            .with_call::<IHyperchain::getL2SystemContractsUpgradeTxHashCall>(
+                IHyperchain::getL2SystemContractsUpgradeTxHashCall::abi_encode_returns(&(
+                    B256::ZERO,
+                )),
+            )
+            .with_call::<IHyperchain::getChainIdCall>(
+                IHyperchain::getChainIdCall::abi_encode_returns(&(U256::from(270),)),
+            )
+            .with_call::<IHyperchain::getSettlementLayerCall>(
+                IHyperchain::getSettlementLayerCall::abi_encode_returns(&(Address::ZERO,)),
+            )
+            .with_call::<IHyperchain::getPriorityQueueSizeCall>(
+                IHyperchain::getPriorityQueueSizeCall::abi_encode_returns(&(U256::from(3),)),
+            )
+            .with_call::<IHyperchain::getTotalPriorityTxsCall>(
+                IHyperchain::getTotalPriorityTxsCall::abi_encode_returns(&(U256::from(7),)),
+            )
+            .with_call::<IHyperchain::getPriorityTreeRootCall>(
+                IHyperchain::getPriorityTreeRootCall::abi_encode_returns(&(b256!(
+                    "00000000000000000000000000000000000000000000000000000000000000aa"
+                ),)),
+            )
+    }
+
+    #[test]
+    fn priority_scan_of_empty_queue_is_complete() {
+        let scan = PriorityScan::new(&[], U256::ZERO);
+        assert!(scan.complete);
+        assert_eq!(scan.min_index, None);
+    }
+
+    #[test]
+    fn priority_scan_covering_whole_queue_is_complete() {
+        let scan = PriorityScan::new(&[2, 0, 1], U256::from(3));
+        assert!(scan.complete);
+        assert_eq!(scan.fetched, 3);
+        assert_eq!(scan.min_index, Some(0));
+        assert_eq!(scan.max_index, Some(2));
+    }
+
+    #[test]
+    fn priority_scan_missing_old_transactions_is_incomplete() {
+        let scan = PriorityScan::new(&[3, 4], U256::from(5));
+        assert!(!scan.complete);
+        assert_eq!(scan.min_index, Some(3));
+    }
+
+    #[test]
+    fn priority_scan_missing_recent_transactions_is_incomplete() {
+        assert!(!PriorityScan::new(&[0, 1], U256::from(3)).complete);
+    }
+
+    #[tokio::test]
+    async fn parses_hyperchain_state() {
+        let provider = hyperchain_mock()
+            .with_call::<IHyperchain::getPubdataPricingModeCall>(
+                IHyperchain::getPubdataPricingModeCall::abi_encode_returns(&(1u8,)),
+            )
+            .provider();
+
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        assert_eq!(st.hyperchain, HYPERCHAIN);
+        assert_eq!(st.implementation, None);
+        assert_eq!(st.code.size, 2);
+        assert_eq!(
+            st.verifier,
+            address!("2000000000000000000000000000000000000002")
+        );
+        assert_eq!(
+            st.admin(),
+            address!("3000000000000000000000000000000000000003")
+        );
+        assert_eq!(st.total_batches_committed, U256::from(12));
+        assert_eq!(st.total_batches_verified, U256::from(11));
+        assert_eq!(st.total_batches_executed, U256::from(10));
+        assert_eq!(st.commit_to_verify_lag(), U256::from(1));
+        assert_eq!(st.verify_to_execute_lag(), U256::from(1));
+        assert_eq!(st.batch_lag(), U256::from(2));
+        assert_eq!(st.protocol_version(), (0, 26, 0));
+        assert_eq!(
+            st.to_report().priority_tree_root,
+            Some("0x00000000000000000000000000000000000000000000000000000000000000aa".to_string())
+        );
+        assert_eq!(st.chain_id, U256::from(270));
+        assert_eq!(st.unprocessed_queue_size, U256::from(3));
+        assert_eq!(st.total_queue_size, U256::from(7));
+        assert_eq!(st.processed_queue_size(), U256::from(4));
+        assert!(st.is_priority_processed(3));
+        assert!(!st.is_priority_processed(4));
+        assert!(!st.is_passive());
+        assert_eq!(st.to_report().da_mode, "validium");
+    }
+
+    #[tokio::test]
+    async fn batch_status_follows_the_counters() {
+        let provider = hyperchain_mock().provider();
+
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        assert_eq!(st.batch_status(0), BatchStatus::Executed);
+        assert_eq!(st.batch_status(10), BatchStatus::Executed);
+        assert_eq!(st.batch_status(11), BatchStatus::Verified);
+        assert_eq!(st.batch_status(12), BatchStatus::Committed);
+        assert_eq!(st.batch_status(13), BatchStatus::NotCommitted);
+    }
+
+    #[tokio::test]
+    async fn priority_tree_root_errors_are_not_swallowed() {
+        let provider = hyperchain_mock()
+            .with_call::<IHyperchain::getPriorityTreeRootCall>(vec![])
+            .provider();
+
+        assert!(StateTransition::new(&provider, HYPERCHAIN).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn missing_pubdata_pricing_mode_is_unknown() {
+        let provider = hyperchain_mock().provider();
+
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        assert_eq!(st.to_report().da_mode, "unknown");
+    }
+
+    #[tokio::test]
+    async fn reports_da_validator_pair() {
+        let provider = hyperchain_mock()
+            .with_call::<IHyperchain::getDAValidatorPairCall>(
+                IHyperchain::getDAValidatorPairCall::abi_encode_returns(&(
+                    address!("5000000000000000000000000000000000000005"),
+                    address!("6000000000000000000000000000000000000006"),
+                )),
+            )
+            .provider();
+
+        let report = StateTransition::new(&provider, HYPERCHAIN)
+            .await
+            .unwrap()
+            .to_report();
+
+        assert_eq!(
+            report.l1_da_validator,
+            "0x5000000000000000000000000000000000000005"
+        );
+        assert_eq!(
+            report.l2_da_validator,
+            "0x6000000000000000000000000000000000000006"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_da_validator_pair_is_none() {
+        let provider = hyperchain_mock().provider();
+
+        let report = StateTransition::new(&provider, HYPERCHAIN)
+            .await
+            .unwrap()
+            .to_report();
+
+        assert_eq!(report.l1_da_validator, "none");
+        assert_eq!(report.l2_da_validator, "none");
+    }
+
+    #[tokio::test]
+    async fn processed_queue_size_does_not_underflow() {
+        let provider = hyperchain_mock()
+            .with_call::<IHyperchain::getPriorityQueueSizeCall>(
+                IHyperchain::getPriorityQueueSizeCall::abi_encode_returns(&(U256::from(9),)),
+            )
+            .provider();
+
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        assert_eq!(st.processed_queue_size(), U256::ZERO);
+        assert_eq!(st.to_report().queue.processed, "0");
+    }
+
+    #[tokio::test]
+    async fn fails_when_a_required_getter_reverts() {
+        let provider = MockTransport::default()
+            .with_response("eth_getStorageAt", json!("0x0"))
+            .with_response("eth_getCode", json!("0x"))
+            .provider();
+
+        assert!(StateTransition::new(&provider, HYPERCHAIN).await.is_err());
+    }
+
+    #[test]
+    fn selects_contract_layout_by_protocol_version() {
+        let layout = ContractLayout::for_protocol_version;
+        assert_eq!(layout((0, 23, 0)), ContractLayout::Legacy);
+        assert_eq!(layout((0, 25, 1)), ContractLayout::PreGateway);
+        assert_eq!(layout((0, 26, 0)), ContractLayout::Gateway);
+        assert_eq!(layout((0, 28, 0)), ContractLayout::Gateway);
+        assert_eq!(layout((0, 29, 0)), ContractLayout::Newer);
+        assert_eq!(layout((1, 0, 0)), ContractLayout::Newer);
+        assert_eq!(ContractLayout::Gateway.warning((0, 26, 0)), None);
+    }
+
+    #[test]
+    fn parses_legacy_protocol_versions() {
+        assert_eq!(legacy_protocol_version(U256::from(22)), (0, 22, 0));
+        assert_eq!(legacy_protocol_version(pack_semver((0, 23, 2))), (0, 23, 2));
+    }
+
+    #[tokio::test]
+    async fn newer_protocol_versions_are_read_with_a_warning() {
+        let provider = hyperchain_mock()
+            .with_call::<IHyperchain::getSemverProtocolVersionCall>(
+                IHyperchain::getSemverProtocolVersionCall::abi_encode_returns(&(0u32, 29u32, 0u32)),
+            )
+            .provider();
+
+        let report = StateTransition::new(&provider, HYPERCHAIN)
+            .await
+            .unwrap()
+            .to_report();
+
+        assert_eq!(report.contract_layout, "newer");
+        assert!(report.layout_warning.unwrap().contains("0.29.0"));
+        assert_eq!(
+            report.priority_tree_root.as_deref(),
+            Some("0x00000000000000000000000000000000000000000000000000000000000000aa")
+        );
+    }
+
+    #[tokio::test]
+    async fn pre_gateway_chains_skip_the_gateway_getters() {
+        // getPriorityTreeRoot reverts on v25 - it must not be called.
+        let provider = hyperchain_mock()
+            .with_call::<IHyperchain::getSemverProtocolVersionCall>(
+                IHyperchain::getSemverProtocolVersionCall::abi_encode_returns(&(0u32, 25u32, 1u32)),
+            )
+            .with_call::<IHyperchain::getPriorityTreeRootCall>(vec![])
+            .provider();
+
+        let report = StateTransition::new(&provider, HYPERCHAIN)
+            .await
+            .unwrap()
+            .to_report();
+
+        assert_eq!(report.contract_layout, "pre-gateway");
+        assert_eq!(report.layout_warning, None);
+        assert_eq!(report.priority_tree_root, None);
+    }
+
+    #[tokio::test]
+    async fn legacy_chain_failures_mention_the_partial_support() {
+        let provider = MockTransport::default()
+            .with_response("eth_getStorageAt", json!("0x0"))
+            .with_response("eth_getCode", json!("0x"))
+            .with_call::<IHyperchain::getProtocolVersionCall>(
+                IHyperchain::getProtocolVersionCall::abi_encode_returns(&(U256::from(22),)),
+            )
+            .provider();
+
+        let err = StateTransition::new(&provider, HYPERCHAIN)
+            .await
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("protocol v22 which this tool supports partially"));
+    }
+
+    #[test]
+    fn packs_semver() {
+        let packed = pack_semver((0, 26, 3));
+        assert_eq!(packed, (U256::from(26) << 32) + U256::from(3));
+        assert_eq!(unpack_semver(packed), (0, 26, 3));
+    }
+
+    fn ctm_mock(latest: (u32, u32, u32), deadline: U256) -> MockTransport {
+        hyperchain_mock()
+            .with_call::<IHyperchain::getChainTypeManagerCall>(
+                IHyperchain::getChainTypeManagerCall::abi_encode_returns(&(address!(
+                    "4000000000000000000000000000000000000004"
+                ),)),
+            )
+            .with_call::<IChainTypeManager::protocolVersionCall>(
+                IChainTypeManager::protocolVersionCall::abi_encode_returns(&(pack_semver(latest),)),
+            )
+            .with_call::<IChainTypeManager::protocolVersionDeadlineCall>(
+                IChainTypeManager::protocolVersionDeadlineCall::abi_encode_returns(&(deadline,)),
+            )
+    }
+
+    #[tokio::test]
+    async fn no_pending_upgrade_when_chain_is_on_latest_version() {
+        let provider = ctm_mock((0, 26, 0), U256::MAX).provider();
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        assert!(st.pending_upgrade(&provider, 1000).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn reports_pending_upgrade_and_its_deadline() {
+        let provider = ctm_mock((0, 27, 0), U256::from(2000)).provider();
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        let upgrade = st.pending_upgrade(&provider, 1000).await.unwrap().unwrap();
+        assert_eq!(upgrade.target_version, "0.27.0");
+        assert_eq!(upgrade.deadline_unix, Some(2000));
+        assert!(!upgrade.past_deadline);
+
+        let upgrade = st.pending_upgrade(&provider, 3000).await.unwrap().unwrap();
+        assert!(upgrade.past_deadline);
+    }
+
+    #[tokio::test]
+    async fn pending_upgrade_without_deadline_is_never_overdue() {
+        let provider = ctm_mock((0, 27, 0), U256::MAX).provider();
+        let st = StateTransition::new(&provider, HYPERCHAIN).await.unwrap();
+
+        let upgrade = st.pending_upgrade(&provider, 3000).await.unwrap().unwrap();
+        assert_eq!(upgrade.deadline_unix, None);
+        assert!(!upgrade.past_deadline);
     }
 }
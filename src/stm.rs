@@ -2,14 +2,16 @@ use std::fmt::Display;
 
 use alloy::{
     primitives::{Address, FixedBytes, U256},
-    providers::Provider,
-    rpc::types::Filter,
     sol,
     sol_types::SolEvent,
 };
 use colored::Colorize;
 
-use crate::{bridgehub::IBridgehub, sequencer::Sequencer, utils::get_human_name_for};
+use crate::{
+    bridgehub::IBridgehub,
+    sequencer::Sequencer,
+    utils::{format_implementation, get_all_events, get_human_name_for, get_proxy_implementation},
+};
 
 sol! {
     #[sol(rpc)]
@@ -19,21 +21,39 @@ sol! {
         function BRIDGE_HUB() external view returns (address);
         function admin() external view returns (address);
         function owner() external view returns (address);
+        // Latest protocol version (packed semver) and the timestamp until which an older
+        // version is still supported.
+        function protocolVersion() external view returns (uint256);
+        function protocolVersionDeadline(uint256 _protocolVersion) external view returns (uint256);
     }
 }
 
 pub struct ChainTypeManager {
     pub address: Address,
+    pub implementation: Option<Address>,
     pub bridgehub: Address,
     pub admin: Address,
     pub owner: Address,
     pub asset_id: FixedBytes<32>,
     pub asset_name: String,
+    // Chains that finished migrating to this CTM (from the recent MigrationFinalized events).
+    pub migrations: Vec<MigrationRecord>,
+}
+
+pub struct MigrationRecord {
+    pub chain_id: U256,
+    pub asset_id: FixedBytes<32>,
+    pub zk_chain: Address,
+    pub block: Option<u64>,
 }
 
 impl ChainTypeManager {
     pub async fn new(sequencer: &Sequencer, address: Address) -> Self {
         let provider = sequencer.get_provider();
+        let implementation = get_proxy_implementation(&provider, address)
+            .await
+            .ok()
+            .flatten();
         let contract = IChainTypeManager::new(address, provider);
 
         let bridgehub = contract.BRIDGE_HUB().call().await.unwrap()._0;
@@ -52,24 +72,90 @@ impl ChainTypeManager {
             ._0;
         let asset_name = get_human_name_for(asset_id);
 
-        Self {
+        let mut ctm = Self {
             address,
+            implementation,
             bridgehub,
             admin,
             owner,
             asset_id,
             asset_name,
+            migrations: vec![],
+        };
+        match ctm.migration_history(sequencer).await {
+            Ok(migrations) => ctm.migrations = migrations,
+            Err(err) => progress!(
+                "{} Failed to fetch migration history of CTM {}: {}",
+                "[WARN]".yellow(),
+                address,
+                err
+            ),
         }
+        ctm
+    }
+
+    // Decodes the MigrationFinalized events (from the recent blocks), oldest first.
+    pub async fn migration_history(
+        &self,
+        sequencer: &Sequencer,
+    ) -> eyre::Result<Vec<MigrationRecord>> {
+        let events = get_all_events(
+            sequencer,
+            self.address,
+            IChainTypeManager::MigrationFinalized::SIGNATURE_HASH,
+            5000, // 5k block limit
+        )
+        .await?;
+
+        let mut result = events
+            .into_iter()
+            .map(|log| {
+                let block = log.block_number;
+                let event = log
+                    .log_decode::<IChainTypeManager::MigrationFinalized>()?
+                    .inner
+                    .data;
+                eyre::Ok(MigrationRecord {
+                    chain_id: event.chainId,
+                    asset_id: event.assetId,
+                    zk_chain: event.zkChain,
+                    block,
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        result.sort_by_key(|record| record.block);
+        Ok(result)
     }
 
     pub fn detailed_fmt(&self, f: &mut std::fmt::Formatter<'_>, pad: usize) -> std::fmt::Result {
         let pad = " ".repeat(pad);
         writeln!(f, "{}=== CTM -     {}", pad, self.asset_name.bold().white())?;
         writeln!(f, "{}   Address:   {}", pad, self.address)?;
+        writeln!(
+            f,
+            "{}   Impl:      {}",
+            pad,
+            format_implementation(self.implementation)
+        )?;
         writeln!(f, "{}   Asset id:  {}", pad, self.asset_id)?;
         writeln!(f, "{}   Bridgehub: {}", pad, self.bridgehub)?;
         writeln!(f, "{}   Admin:     {}", pad, self.admin)?;
         writeln!(f, "{}   Owner:     {}", pad, self.owner)?;
+        if !self.migrations.is_empty() {
+            writeln!(f, "{}   Migrations:", pad)?;
+            for migration in &self.migrations {
+                writeln!(
+                    f,
+                    "{}     Chain {} -> {} (block {})",
+                    pad,
+                    migration.chain_id,
+                    migration.zk_chain,
+                    migration
+                        .block
+                        .map_or("unknown".to_string(), |block| block.to_string())
+                )?;
+            }
+        }
 
         Ok(())
     }
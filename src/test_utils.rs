@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use alloy::{
+    primitives::hex,
+    providers::RootProvider,
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, Response, ResponsePacket, SerializedRequest},
+    },
+    sol_types::SolCall,
+    transports::{Transport, TransportError, TransportErrorKind, TransportFut},
+};
+use serde_json::{json, Value};
+use tower::Service;
+
+use crate::sequencer::SequencerProvider;
+use crate::transport::FailoverTransport;
+
+// Transport that answers with canned responses instead of talking to a node.
+// eth_call responses are keyed by the function selector, everything else by the method name.
+// Requests without a canned response fail (like a revert would).
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    responses: Arc<HashMap<String, Value>>,
+    // Batches are rejected (like by many public endpoints) unless enabled.
+    batches: bool,
+    // Batches fail like a dropped connection would, instead of being rejected.
+    failing_batches: bool,
+    // Number of packets (i.e. round trips) received so far.
+    packets: Arc<AtomicUsize>,
+}
+
+impl MockTransport {
+    pub fn with_response(mut self, method: &str, result: Value) -> Self {
+        Arc::make_mut(&mut self.responses).insert(method.to_string(), result);
+        self
+    }
+
+    // Answers calls to `C` (on any address) with the given abi encoded return data.
+    pub fn with_call<C: SolCall>(self, return_data: Vec<u8>) -> Self {
+        let key = format!("eth_call:0x{}", hex::encode(C::SELECTOR));
+        self.with_response(&key, json!(format!("0x{}", hex::encode(return_data))))
+    }
+
+    pub fn with_batches(mut self) -> Self {
+        self.batches = true;
+        self
+    }
+
+    pub fn with_failing_batches(mut self) -> Self {
+        self.failing_batches = true;
+        self
+    }
+
+    pub fn packets(&self) -> usize {
+        self.packets.load(Ordering::Relaxed)
+    }
+
+    pub fn provider(self) -> SequencerProvider {
+        let transport =
+            FailoverTransport::from_transports(vec!["mock".to_string()], vec![self.boxed()]);
+        RootProvider::new(RpcClient::new(transport, false))
+    }
+
+    fn respond(&self, request: &SerializedRequest) -> Result<Response, TransportError> {
+        let key = match request.method() {
+            "eth_call" => {
+                let params: Value = request
+                    .params()
+                    .and_then(|params| serde_json::from_str(params.get()).ok())
+                    .unwrap_or_default();
+                let call = &params[0];
+                let input = call["input"]
+                    .as_str()
+                    .or_else(|| call["data"].as_str())
+                    .unwrap_or_default();
+                format!("eth_call:{}", &input[..input.len().min(10)])
+            }
+            method => method.to_string(),
+        };
+        let result = self
+            .responses
+            .get(&key)
+            .ok_or_else(|| TransportErrorKind::custom_str(&format!("no mock for {}", key)))?;
+
+        let response = json!({"jsonrpc": "2.0", "id": request.id(), "result": result});
+        serde_json::from_value(response.clone())
+            .map_err(|err| TransportError::deser_err(err, response.to_string()))
+    }
+}
+
+impl Service<RequestPacket> for MockTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        self.packets.fetch_add(1, Ordering::Relaxed);
+        let response = match &request {
+            RequestPacket::Single(request) => self.respond(request).map(ResponsePacket::Single),
+            RequestPacket::Batch(requests) if self.batches => requests
+                .iter()
+                .map(|request| self.respond(request))
+                .collect::<Result<_, _>>()
+                .map(ResponsePacket::Batch),
+            RequestPacket::Batch(_) if self.failing_batches => {
+                Err(TransportErrorKind::custom_str("connection reset"))
+            }
+            // A single error response to the whole array.
+            RequestPacket::Batch(_) => serde_json::from_value(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32600, "message": "batches are not supported"}
+            }))
+            .map(ResponsePacket::Single)
+            .map_err(|err| TransportError::deser_err(err, "")),
+        };
+        Box::pin(async move { response })
+    }
+}
@@ -0,0 +1,787 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, OnceLock, RwLock,
+};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use alloy::{
+    primitives::{hex, keccak256},
+    rpc::json_rpc::{
+        Request, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+    },
+    transports::{
+        http::Http, BoxTransport, Transport, TransportError, TransportErrorKind, TransportFut,
+    },
+};
+use colored::Colorize;
+use futures::{
+    channel::{mpsc, oneshot},
+    future::join_all,
+    StreamExt,
+};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use tower::Service;
+
+use crate::statetransition::ContractLayout;
+
+static RECORD_CALLS: AtomicBool = AtomicBool::new(false);
+// Set with --dry-run: requests are recorded and answered with placeholders, nothing is sent.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+// Set with --batch-rpc: new transports coalesce their requests into JSON-RPC batches.
+static BATCH_REQUESTS: AtomicBool = AtomicBool::new(false);
+
+// Requests issued within this window are sent together (up to MAX_BATCH_SIZE of them).
+const BATCH_WINDOW: Duration = Duration::from_millis(5);
+const MAX_BATCH_SIZE: usize = 50;
+
+lazy_static! {
+    // Number of calls per (method, target contract), filled only when recording is enabled.
+    static ref RPC_CALLS: Mutex<BTreeMap<(String, String), usize>> = Mutex::new(BTreeMap::new());
+    // Round-trip times per rpc url, always collected (it's just a couple of numbers per url).
+    static ref ENDPOINT_LATENCIES: Mutex<BTreeMap<String, EndpointLatency>> =
+        Mutex::new(BTreeMap::new());
+    // Which JSON-RPC methods may be sent at all - everything is allowed if not set.
+    static ref METHOD_POLICY: RwLock<Option<MethodPolicy>> = RwLock::new(None);
+}
+
+// Guardrail on the JSON-RPC methods that the tool sends: with `allow` set only those
+// methods go out, and anything in `deny` never does.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MethodPolicy {
+    #[serde(default)]
+    pub allow: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub deny: BTreeSet<String>,
+}
+
+impl MethodPolicy {
+    fn check(&self, method: &str) -> Result<(), String> {
+        if self.deny.contains(method) {
+            return Err(format!(
+                "RPC method {} is denied by the method policy",
+                method
+            ));
+        }
+        match &self.allow {
+            Some(allow) if !allow.contains(method) => Err(format!(
+                "RPC method {} is not in the method policy allowlist",
+                method
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn load_method_policy(path: &Path) -> eyre::Result<()> {
+    let policy: MethodPolicy = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|err| eyre::eyre!("Invalid rpc method policy {}: {}", path.display(), err))?;
+    *METHOD_POLICY.write().unwrap() = Some(policy);
+    Ok(())
+}
+
+// Fails (and says so loudly) if the method policy doesn't allow sending this method.
+pub fn check_method(method: &str) -> Result<(), String> {
+    let policy = METHOD_POLICY.read().unwrap();
+    let Some(policy) = policy.as_ref() else {
+        return Ok(());
+    };
+    policy.check(method).inspect_err(|err| {
+        progress!("{} {}", "[ERROR]".red(), err);
+    })
+}
+
+// Round-trip times of the requests sent to a single rpc url (failed ones included).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EndpointLatency {
+    pub requests: usize,
+    pub failures: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl EndpointLatency {
+    fn record(&mut self, elapsed: Duration, failed: bool) {
+        if self.requests == 0 || elapsed < self.min {
+            self.min = elapsed;
+        }
+        self.max = self.max.max(elapsed);
+        self.total += elapsed;
+        self.requests += 1;
+        if failed {
+            self.failures += 1;
+        }
+    }
+
+    pub fn avg(&self) -> Duration {
+        if self.requests == 0 {
+            return Duration::ZERO;
+        }
+        self.total / self.requests as u32
+    }
+}
+
+pub fn endpoint_latencies() -> BTreeMap<String, EndpointLatency> {
+    ENDPOINT_LATENCIES.lock().unwrap().clone()
+}
+
+// Starts a fresh measurement (e.g. on every watch mode tick).
+pub fn reset_endpoint_latencies() {
+    ENDPOINT_LATENCIES.lock().unwrap().clear();
+}
+
+fn record_latency(url: &str, elapsed: Duration, failed: bool) {
+    ENDPOINT_LATENCIES
+        .lock()
+        .unwrap()
+        .entry(url.to_string())
+        .or_default()
+        .record(elapsed, failed);
+}
+
+pub fn set_batch_requests(batch: bool) {
+    BATCH_REQUESTS.store(batch, Ordering::Relaxed);
+}
+
+pub fn batch_requests() -> bool {
+    BATCH_REQUESTS.load(Ordering::Relaxed)
+}
+
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+// The cheap reads that a dry run still sends: the detection, the chain lists and the asset
+// registrations, which the per-chain and per-asset calls are made for. Everything else is
+// answered by dry_run_response.
+fn sent_in_dry_run(request: &SerializedRequest) -> bool {
+    match request.method() {
+        "eth_chainId" | "eth_blockNumber" => true,
+        "eth_call" => call_selector(request) == selector("getAllZKChainChainIDs()"),
+        "eth_getLogs" => {
+            let asset_registered =
+                keccak256("AssetRegistered(bytes32,address,bytes32,address)").to_string();
+            logs_topic(request).eq_ignore_ascii_case(&asset_registered)
+        }
+        _ => false,
+    }
+}
+
+fn selector(signature: &str) -> String {
+    hex::encode(&keccak256(signature)[..4])
+}
+
+// Function selector of an eth_call (hex, without the 0x prefix).
+fn call_selector(request: &SerializedRequest) -> String {
+    let params = request
+        .params()
+        .and_then(|params| serde_json::from_str::<serde_json::Value>(params.get()).ok())
+        .unwrap_or_default();
+    let input = params[0]["input"]
+        .as_str()
+        .or_else(|| params[0]["data"].as_str())
+        .unwrap_or_default();
+    input.get(2..10).unwrap_or_default().to_lowercase()
+}
+
+// Event signature (first topic) that an eth_getLogs filter asks for, empty if none.
+fn logs_topic(request: &SerializedRequest) -> String {
+    let params = request
+        .params()
+        .and_then(|params| serde_json::from_str::<serde_json::Value>(params.get()).ok())
+        .unwrap_or_default();
+    let topic = &params[0]["topics"][0];
+    // A single topic is sent as a plain value, several as a list.
+    topic
+        .as_str()
+        .or_else(|| topic[0].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+// Placeholder answer for a request that isn't sent: zeros and empty results, i.e. chains on
+// the latest known protocol version, settling on L1, without priority transactions or tokens.
+fn dry_run_response(request: &SerializedRequest) -> Result<Response, TransportError> {
+    let result = match request.method() {
+        "eth_chainId" => serde_json::json!("0x1"),
+        "eth_blockNumber" => serde_json::json!("0x1000000"),
+        "eth_getLogs" => serde_json::json!([]),
+        "eth_call" => serde_json::json!(dry_run_call_result(request)),
+        "eth_getCode" => serde_json::json!("0x00"),
+        "eth_getStorageAt" => serde_json::json!(format!("0x{}", "00".repeat(32))),
+        "eth_getBalance" | "eth_getTransactionCount" | "eth_gasPrice" => {
+            serde_json::json!("0x0")
+        }
+        _ => serde_json::Value::Null,
+    };
+    let response = serde_json::json!({"jsonrpc": "2.0", "id": request.id(), "result": result});
+    serde_json::from_value(response.clone())
+        .map_err(|err| TransportError::deser_err(err, response.to_string()))
+}
+
+// Zero / empty return data of the called function. The answers are decoded strictly, so the
+// getters that don't return a single word (an address, number or hash) are listed here.
+fn dry_run_call_result(request: &SerializedRequest) -> String {
+    let word = |value: u32| format!("{:064x}", value);
+    // Offset of the data, and its length of 0.
+    let empty_dynamic = word(32) + &word(0);
+
+    let called = call_selector(request);
+    let is = |signature: &str| called == selector(signature);
+    let result = if [
+        "getAllZKChainChainIDs()",
+        "getERC20Getters(address)",
+        "name()",
+        "symbol()",
+    ]
+    .into_iter()
+    .any(is)
+    {
+        empty_dynamic
+    } else if is("getSemverProtocolVersion()") {
+        // So that all the getters of the current layout are read.
+        word(0) + &word(ContractLayout::LATEST_KNOWN_VERSION) + &word(0)
+    } else if is("getDAValidatorPair()") {
+        word(0).repeat(2)
+    } else {
+        word(0)
+    };
+    format!("0x{}", result)
+}
+
+pub fn set_record_calls(record: bool) {
+    RECORD_CALLS.store(record, Ordering::Relaxed);
+}
+
+pub fn recorded_calls() -> BTreeMap<(String, String), usize> {
+    RPC_CALLS.lock().unwrap().clone()
+}
+
+pub fn record_call(method: &str, target: &str) {
+    if RECORD_CALLS.load(Ordering::Relaxed) {
+        *RPC_CALLS
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), target.to_string()))
+            .or_default() += 1;
+    }
+}
+
+// Contract that the request is about - `to` of eth_call, or the address argument of
+// calls like eth_getCode / eth_getStorageAt. Empty if there is none.
+fn request_target(request: &SerializedRequest) -> String {
+    let params = request
+        .params()
+        .and_then(|params| serde_json::from_str::<serde_json::Value>(params.get()).ok());
+    let first = params
+        .as_ref()
+        .and_then(|params| params.as_array())
+        .and_then(|params| params.first());
+    match first {
+        Some(serde_json::Value::String(address)) if address.len() == 42 => address.clone(),
+        Some(serde_json::Value::Object(call)) => call
+            .get("to")
+            .or_else(|| call.get("address"))
+            .and_then(|to| to.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+// Position of the block argument of the state reading methods that get pinned.
+fn block_param_index(method: &str) -> Option<usize> {
+    match method {
+        "eth_call" | "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" => Some(1),
+        "eth_getStorageAt" => Some(2),
+        _ => None,
+    }
+}
+
+// Replaces the (missing or `latest`) block argument of a state read with the pinned block.
+// Requests that already ask for a specific block are left as they are.
+fn pin_request(request: SerializedRequest, block: u64) -> SerializedRequest {
+    let Some(index) = block_param_index(request.method()) else {
+        return request;
+    };
+    let Some(mut params) = request
+        .params()
+        .and_then(|params| serde_json::from_str::<Vec<serde_json::Value>>(params.get()).ok())
+    else {
+        return request;
+    };
+
+    let pinned = serde_json::Value::String(format!("{:#x}", block));
+    match params.get(index) {
+        None if params.len() == index => params.push(pinned),
+        Some(serde_json::Value::String(tag)) if tag == "latest" => params[index] = pinned,
+        _ => return request,
+    }
+
+    let pinned_request = serde_json::value::to_raw_value(&params).and_then(|params| {
+        Request::new(request.method().to_string(), request.id().clone(), params).serialize()
+    });
+    pinned_request.unwrap_or(request)
+}
+
+// Transport that sends requests to one of several RPC urls, failing over to the next url
+// when a request errors (connection problems, rate limits etc). The url that answered last
+// is tried first for the following requests.
+#[derive(Clone)]
+pub struct FailoverTransport {
+    urls: Arc<Vec<String>>,
+    transports: Arc<Vec<BoxTransport>>,
+    current: Arc<AtomicUsize>,
+    // If set, state reads are done at this block rather than at the latest one.
+    pinned_block: Option<u64>,
+    batching: Option<Arc<Batching>>,
+}
+
+type PendingRequest = (
+    SerializedRequest,
+    oneshot::Sender<Result<Response, TransportError>>,
+);
+
+// Queue of the requests waiting to be sent in the next batch. The task sending the batches
+// is started with the first request (so that the transport can be built outside of a runtime)
+// and stops once all the clones of the transport are dropped.
+#[derive(Default)]
+struct Batching {
+    queue: OnceLock<mpsc::UnboundedSender<PendingRequest>>,
+    // Set once the endpoint rejected a batch - everything is sent individually from then on.
+    rejected: Arc<AtomicBool>,
+}
+
+impl FailoverTransport {
+    pub fn new(urls: &[String]) -> eyre::Result<Self> {
+        if urls.is_empty() {
+            eyre::bail!("At least one rpc url is required");
+        }
+        let transports = urls
+            .iter()
+            .map(|url| Ok(Http::new(url.parse()?).boxed()))
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            urls: Arc::new(urls.to_vec()),
+            transports: Arc::new(transports),
+            current: Arc::new(AtomicUsize::new(0)),
+            pinned_block: None,
+            batching: None,
+        })
+    }
+
+    // Transport backed by arbitrary (e.g. mocked) transports instead of http ones.
+    #[cfg(test)]
+    pub fn from_transports(urls: Vec<String>, transports: Vec<BoxTransport>) -> Self {
+        Self {
+            urls: Arc::new(urls),
+            transports: Arc::new(transports),
+            current: Arc::new(AtomicUsize::new(0)),
+            pinned_block: None,
+            batching: None,
+        }
+    }
+
+    pub fn with_pinned_block(mut self, pinned_block: Option<u64>) -> Self {
+        self.pinned_block = pinned_block;
+        self
+    }
+
+    // Coalesces the requests issued within BATCH_WINDOW into a single JSON-RPC batch.
+    pub fn with_batching(mut self, batching: bool) -> Self {
+        self.batching = batching.then(Default::default);
+        self
+    }
+
+    // Url of the endpoint that will be tried first.
+    pub fn url(&self) -> &str {
+        &self.urls[self.current.load(Ordering::Relaxed)]
+    }
+
+    // Sends the packet as is, failing over between the urls.
+    async fn send(self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let start = self.current.load(Ordering::Relaxed);
+        let count = self.transports.len();
+        let mut last_error = None;
+
+        for offset in 0..count {
+            let index = (start + offset) % count;
+            let mut transport = self.transports[index].clone();
+            let started = Instant::now();
+            let result = transport.call(request.clone()).await;
+            record_latency(&self.urls[index], started.elapsed(), result.is_err());
+            match result {
+                Ok(response) => {
+                    self.current.store(index, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Err(err) => {
+                    if count > 1 {
+                        progress!(
+                            "Request to {} failed ({}), trying next rpc url",
+                            self.urls[index],
+                            err
+                        );
+                    }
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.expect("at least one transport"))
+    }
+
+    // Queues the request for the next batch, gives it back if it has to be sent on its own.
+    fn enqueue(
+        &self,
+        request: SerializedRequest,
+    ) -> Result<TransportFut<'static>, SerializedRequest> {
+        let Some(batching) = &self.batching else {
+            return Err(request);
+        };
+        if batching.rejected.load(Ordering::Relaxed) {
+            return Err(request);
+        }
+        let queue = batching.queue.get_or_init(|| {
+            let (sender, receiver) = mpsc::unbounded();
+            // Without the queue, so that the task doesn't keep its own sender alive.
+            let transport = FailoverTransport {
+                batching: None,
+                ..self.clone()
+            };
+            tokio::spawn(transport.run_batches(batching.rejected.clone(), receiver));
+            sender
+        });
+        let (sender, receiver) = oneshot::channel();
+        if let Err(err) = queue.unbounded_send((request, sender)) {
+            return Err(err.into_inner().0);
+        }
+        Ok(Box::pin(async move {
+            let response = receiver
+                .await
+                .map_err(|_| TransportErrorKind::custom_str("batch was dropped"))??;
+            Ok(ResponsePacket::Single(response))
+        }))
+    }
+
+    async fn run_batches(
+        self,
+        rejected: Arc<AtomicBool>,
+        mut receiver: mpsc::UnboundedReceiver<PendingRequest>,
+    ) {
+        while let Some(first) = receiver.next().await {
+            tokio::time::sleep(BATCH_WINDOW).await;
+            let mut pending = vec![first];
+            while pending.len() < MAX_BATCH_SIZE {
+                match receiver.try_next() {
+                    Ok(Some(request)) => pending.push(request),
+                    _ => break,
+                }
+            }
+            tokio::spawn(self.clone().send_batch(rejected.clone(), pending));
+        }
+    }
+
+    async fn send_batch(self, rejected: Arc<AtomicBool>, pending: Vec<PendingRequest>) {
+        let (requests, senders): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+        if let ([request], [_]) = (requests.as_slice(), senders.as_slice()) {
+            let response = self
+                .send(RequestPacket::Single(request.clone()))
+                .await
+                .and_then(single_response);
+            let _ = senders
+                .into_iter()
+                .next()
+                .map(|sender| sender.send(response));
+            return;
+        }
+        match self
+            .clone()
+            .send(RequestPacket::Batch(requests.clone()))
+            .await
+        {
+            Ok(ResponsePacket::Batch(mut responses)) => {
+                for (request, sender) in requests.iter().zip(senders) {
+                    let response = responses
+                        .iter()
+                        .position(|response| response.id == *request.id())
+                        .map(|position| responses.swap_remove(position))
+                        .ok_or_else(|| {
+                            TransportErrorKind::custom_str(&format!(
+                                "No response for {} in the batch",
+                                request.method()
+                            ))
+                        });
+                    let _ = sender.send(response);
+                }
+            }
+            // The request failed (e.g. a dropped connection) or the endpoint doesn't do batches -
+            // send them one by one. Only a reply to the array that isn't a batch (usually a
+            // single error response) stops batching for good.
+            result => {
+                if let Ok(ResponsePacket::Single(response)) = &result {
+                    if !rejected.swap(true, Ordering::Relaxed) {
+                        progress!(
+                            "{} {} rejected a JSON-RPC batch ({}), sending requests individually",
+                            "[WARN]".yellow(),
+                            self.url(),
+                            match &response.payload {
+                                ResponsePayload::Failure(err) => err.to_string(),
+                                ResponsePayload::Success(_) => "no batch response".to_string(),
+                            }
+                        );
+                    }
+                }
+                let responses = join_all(
+                    requests
+                        .into_iter()
+                        .map(|request| self.clone().send(RequestPacket::Single(request))),
+                )
+                .await;
+                for (response, sender) in responses.into_iter().zip(senders) {
+                    let _ = sender.send(response.and_then(single_response));
+                }
+            }
+        }
+    }
+}
+
+fn single_response(response: ResponsePacket) -> Result<Response, TransportError> {
+    match response {
+        ResponsePacket::Single(response) => Ok(response),
+        ResponsePacket::Batch(_) => {
+            Err(TransportErrorKind::custom_str("Unexpected batch response"))
+        }
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let this = self.clone();
+        let request = match (self.pinned_block, request) {
+            (Some(block), RequestPacket::Single(request)) => {
+                RequestPacket::Single(pin_request(request, block))
+            }
+            (Some(block), RequestPacket::Batch(requests)) => RequestPacket::Batch(
+                requests
+                    .into_iter()
+                    .map(|request| pin_request(request, block))
+                    .collect(),
+            ),
+            (None, request) => request,
+        };
+        let requests = match &request {
+            RequestPacket::Single(request) => std::slice::from_ref(request),
+            RequestPacket::Batch(requests) => requests.as_slice(),
+        };
+        if let Err(err) = requests
+            .iter()
+            .try_for_each(|request| check_method(request.method()))
+        {
+            return Box::pin(async move { Err(TransportErrorKind::custom_str(&err)) });
+        }
+        for request in requests {
+            record_call(request.method(), &request_target(request));
+        }
+        // Batching is off in a dry run, so only single requests are sent.
+        let placeholder = match &request {
+            _ if !dry_run() => None,
+            RequestPacket::Single(request) if sent_in_dry_run(request) => None,
+            RequestPacket::Single(request) => {
+                Some(dry_run_response(request).map(ResponsePacket::Single))
+            }
+            RequestPacket::Batch(requests) => Some(
+                requests
+                    .iter()
+                    .map(dry_run_response)
+                    .collect::<Result<_, _>>()
+                    .map(ResponsePacket::Batch),
+            ),
+        };
+        if let Some(response) = placeholder {
+            return Box::pin(async move { response });
+        }
+
+        let request = match request {
+            RequestPacket::Single(request) => match this.enqueue(request) {
+                Ok(batched) => return batched,
+                Err(request) => RequestPacket::Single(request),
+            },
+            request => request,
+        };
+        Box::pin(this.send(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        providers::{Provider, RootProvider},
+        rpc::client::RpcClient,
+    };
+
+    use super::*;
+    use crate::test_utils::MockTransport;
+
+    // The fetched values, and whether batching was given up on.
+    async fn fetch_in_parallel(mock: &MockTransport) -> (Vec<u64>, bool) {
+        let transport = FailoverTransport::from_transports(
+            vec!["mock".to_string()],
+            vec![mock.clone().boxed()],
+        )
+        .with_batching(true);
+        let batching = transport.batching.clone().unwrap();
+        let provider = RootProvider::<FailoverTransport>::new(RpcClient::new(transport, false));
+        let (chain_id, block_number) =
+            futures::join!(provider.get_chain_id(), provider.get_block_number());
+        (
+            vec![chain_id.unwrap(), block_number.unwrap()],
+            batching.rejected.load(Ordering::Relaxed),
+        )
+    }
+
+    #[tokio::test]
+    async fn batches_parallel_requests() {
+        let mock = MockTransport::default()
+            .with_response("eth_chainId", serde_json::json!("0x1f4"))
+            .with_response("eth_blockNumber", serde_json::json!("0x10"))
+            .with_batches();
+
+        assert_eq!(fetch_in_parallel(&mock).await, (vec![500, 16], false));
+        assert_eq!(mock.packets(), 1);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_single_requests_when_batches_are_rejected() {
+        let mock = MockTransport::default()
+            .with_response("eth_chainId", serde_json::json!("0x1f4"))
+            .with_response("eth_blockNumber", serde_json::json!("0x10"));
+
+        assert_eq!(fetch_in_parallel(&mock).await, (vec![500, 16], true));
+        // The rejected batch and then each request on its own.
+        assert_eq!(mock.packets(), 3);
+    }
+
+    #[tokio::test]
+    async fn keeps_batching_after_a_failed_batch() {
+        let mock = MockTransport::default()
+            .with_response("eth_chainId", serde_json::json!("0x1f4"))
+            .with_response("eth_blockNumber", serde_json::json!("0x10"))
+            .with_failing_batches();
+
+        assert_eq!(fetch_in_parallel(&mock).await, (vec![500, 16], false));
+        assert_eq!(mock.packets(), 3);
+    }
+
+    fn eth_call(signature: &str) -> SerializedRequest {
+        let input = format!("0x{}", hex::encode(&keccak256(signature)[..4]));
+        Request::new(
+            "eth_call",
+            alloy::rpc::json_rpc::Id::Number(1),
+            serde_json::json!([{"to": "0x0000000000000000000000000000000000010002", "input": input}]),
+        )
+        .serialize()
+        .unwrap()
+    }
+
+    #[test]
+    fn dry_run_answers_match_the_return_types() {
+        let word = 64;
+        assert_eq!(
+            dry_run_call_result(&eth_call("getVerifier()")).len(),
+            2 + word
+        );
+        assert_eq!(
+            dry_run_call_result(&eth_call("getSemverProtocolVersion()")).len(),
+            2 + 3 * word
+        );
+        // An empty string: its offset and a length of 0.
+        let empty = dry_run_call_result(&eth_call("symbol()"));
+        assert_eq!(empty.len(), 2 + 2 * word);
+        assert!(empty[2..2 + word].ends_with("20"));
+    }
+
+    #[test]
+    fn dry_run_sends_only_the_discovery_reads() {
+        assert!(sent_in_dry_run(&eth_call("getAllZKChainChainIDs()")));
+        assert!(!sent_in_dry_run(&eth_call("getVerifier()")));
+        let get_logs = |event: &str| {
+            let filter = alloy::rpc::types::Filter::new().event_signature(keccak256(event));
+            Request::new(
+                "eth_getLogs",
+                alloy::rpc::json_rpc::Id::Number(1),
+                serde_json::json!([filter]),
+            )
+            .serialize()
+            .unwrap()
+        };
+        assert!(sent_in_dry_run(&get_logs(
+            "AssetRegistered(bytes32,address,bytes32,address)"
+        )));
+        assert!(!sent_in_dry_run(&get_logs(
+            "NewPriorityRequest(uint256,bytes32,uint64,(uint256,uint256,uint256,uint256,uint256,uint256,uint256,uint256,uint256,uint256,uint256[4],bytes,bytes,uint256[],bytes,bytes),bytes[])"
+        )));
+    }
+
+    #[test]
+    fn aggregates_endpoint_latency() {
+        let mut latency = EndpointLatency::default();
+        latency.record(Duration::from_millis(30), false);
+        latency.record(Duration::from_millis(10), true);
+        latency.record(Duration::from_millis(20), false);
+
+        assert_eq!(latency.requests, 3);
+        assert_eq!(latency.failures, 1);
+        assert_eq!(latency.min, Duration::from_millis(10));
+        assert_eq!(latency.max, Duration::from_millis(30));
+        assert_eq!(latency.avg(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn method_policy_allows_only_listed_methods() {
+        let policy: MethodPolicy =
+            serde_json::from_str(r#"{"allow": ["eth_call", "eth_chainId"]}"#).unwrap();
+
+        assert_eq!(policy.check("eth_call"), Ok(()));
+        assert_eq!(
+            policy.check("eth_sendRawTransaction"),
+            Err("RPC method eth_sendRawTransaction is not in the method policy allowlist".into())
+        );
+    }
+
+    #[test]
+    fn method_policy_deny_wins() {
+        let policy: MethodPolicy = serde_json::from_str(
+            r#"{"allow": ["eth_call", "eth_getLogs"], "deny": ["eth_getLogs"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.check("eth_call"), Ok(()));
+        assert!(policy.check("eth_getLogs").is_err());
+        assert_eq!(MethodPolicy::default().check("eth_getLogs"), Ok(()));
+    }
+
+    #[test]
+    fn average_of_no_requests_is_zero() {
+        assert_eq!(EndpointLatency::default().avg(), Duration::ZERO);
+    }
+}
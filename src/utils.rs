@@ -1,11 +1,73 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
 use alloy::{
-    primitives::{keccak256, Address, B256},
+    eips::BlockId,
+    primitives::{b256, keccak256, Address, B256, U256},
     providers::Provider,
     rpc::types::{Filter, Log},
+    transports::TransportError,
 };
+use lazy_static::lazy_static;
 use names::{ADJECTIVES, NOUNS};
 
-use crate::sequencer::Sequencer;
+use crate::{
+    addresses::u256_to_address,
+    sequencer::{Sequencer, SequencerProvider},
+};
+
+// bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)
+const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+// Reads the implementation address behind an EIP-1967 proxy.
+// Returns None if the slot is empty (which means that the contract is not a proxy).
+pub async fn get_proxy_implementation(
+    provider: &SequencerProvider,
+    address: Address,
+) -> eyre::Result<Option<Address>> {
+    let value = provider
+        .get_storage_at(address, U256::from_be_bytes(EIP1967_IMPLEMENTATION_SLOT.0))
+        .await?;
+    let implementation = u256_to_address(value);
+    Ok((implementation != Address::ZERO).then_some(implementation))
+}
+
+// Fingerprint of the bytecode deployed at an address, used to notice redeployments between runs.
+#[derive(Clone, Copy, Debug)]
+pub struct ContractCode {
+    pub hash: B256,
+    pub size: usize,
+}
+
+impl ContractCode {
+    pub fn from_bytes(code: &[u8]) -> Self {
+        Self {
+            hash: keccak256(code),
+            size: code.len(),
+        }
+    }
+
+    pub async fn fetch(provider: &SequencerProvider, address: Address) -> eyre::Result<Self> {
+        let code = provider.get_code_at(address).await?;
+        Ok(Self::from_bytes(&code))
+    }
+}
+
+impl std::fmt::Display for ContractCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} bytes)", self.hash, self.size)
+    }
+}
+
+pub fn format_implementation(implementation: Option<Address>) -> String {
+    match implementation {
+        Some(implementation) => implementation.to_string(),
+        None => "not a proxy".to_string(),
+    }
+}
 
 pub async fn get_all_events(
     sequencer: &Sequencer,
@@ -14,7 +76,11 @@ pub async fn get_all_events(
     block_limit: u64,
 ) -> eyre::Result<Vec<Log>> {
     let provider = sequencer.get_provider();
-    let mut current_block = provider.get_block_number().await?;
+    let mut current_block = match sequencer.pinned_block() {
+        Some(block) => block,
+        None => provider.get_block_number().await?,
+    };
+    let archive_provider = sequencer.get_archive_provider();
     let mut result = vec![];
     const BLOCKS_PER_CALL: u64 = 500;
 
@@ -23,13 +89,11 @@ pub async fn get_all_events(
     while current_block > 0 {
         let prev_limit = current_block.saturating_sub(BLOCKS_PER_CALL);
 
-        let filter = Filter::new()
-            .from_block(prev_limit + 1)
-            .to_block(current_block)
-            .event_signature(signature)
-            .address(address);
+        let fetch = |from: u64, to: u64| {
+            get_events_in_range(&archive_provider, address, signature, from, to)
+        };
 
-        let mut logs = sequencer.get_provider().get_logs(&filter).await?;
+        let mut logs = get_logs_splitting(prev_limit + 1, current_block, &fetch).await?;
         result.append(&mut logs);
         current_block = prev_limit;
 
@@ -39,7 +103,163 @@ pub async fn get_all_events(
         }
     }
 
-    Ok(result)
+    Ok(sort_and_dedup_logs(result))
+}
+
+// A single eth_getLogs call for the contract's events in the [from, to] block range.
+async fn get_events_in_range(
+    provider: &SequencerProvider,
+    address: Address,
+    signature: B256,
+    from: u64,
+    to: u64,
+) -> Result<Vec<Log>, TransportError> {
+    let filter = Filter::new()
+        .from_block(from)
+        .to_block(to)
+        .event_signature(signature)
+        .address(address);
+    provider.get_logs(&filter).await
+}
+
+lazy_static! {
+    // (chain id, contract address) -> first block with code at the address.
+    static ref DEPLOYMENT_BLOCKS: Mutex<HashMap<(u64, Address), u64>> = Mutex::new(HashMap::new());
+}
+
+// First block at which the contract has code, found by binary search over eth_getCode (which
+// needs an archive node for old blocks). Cached per chain, as it never changes.
+pub async fn get_deployment_block(sequencer: &Sequencer, address: Address) -> eyre::Result<u64> {
+    let key = (sequencer.chain_id, address);
+    if let Some(block) = DEPLOYMENT_BLOCKS.lock().unwrap().get(&key) {
+        return Ok(*block);
+    }
+
+    let provider = sequencer.get_archive_provider();
+    let latest = match sequencer.pinned_block() {
+        Some(block) => block,
+        None => provider.get_block_number().await?,
+    };
+    let has_code = |block: u64| {
+        let provider = provider.clone();
+        async move {
+            let code = provider
+                .get_code_at(address)
+                .block_id(BlockId::number(block))
+                .await?;
+            eyre::Ok(!code.is_empty())
+        }
+    };
+    let block = first_block_with_code(latest, has_code)
+        .await?
+        .ok_or_else(|| eyre::eyre!("No code at {} (block {})", address, latest))?;
+
+    DEPLOYMENT_BLOCKS.lock().unwrap().insert(key, block);
+    Ok(block)
+}
+
+// Lowest block in [0, latest] for which `has_code` holds (code never disappears, so the
+// predicate is monotonic), None if there is no code even at `latest`.
+async fn first_block_with_code<F, Fut>(latest: u64, has_code: F) -> eyre::Result<Option<u64>>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = eyre::Result<bool>>,
+{
+    if !has_code(latest).await? {
+        return Ok(None);
+    }
+    let (mut low, mut high) = (0, latest);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if has_code(middle).await? {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok(Some(low))
+}
+
+// All the events emitted by the contract since `from_block`. Scans forward in large windows,
+// relying on get_logs_splitting when the node refuses a window.
+pub async fn get_events_since(
+    sequencer: &Sequencer,
+    address: Address,
+    signature: B256,
+    from_block: u64,
+) -> eyre::Result<Vec<Log>> {
+    let latest = match sequencer.pinned_block() {
+        Some(block) => block,
+        None => sequencer.get_provider().get_block_number().await?,
+    };
+    let archive_provider = sequencer.get_archive_provider();
+    let fetch =
+        |from: u64, to: u64| get_events_in_range(&archive_provider, address, signature, from, to);
+    const BLOCKS_PER_CALL: u64 = 50_000;
+
+    let mut result = vec![];
+    let mut current_block = from_block;
+    while current_block <= latest {
+        let to = latest.min(current_block + BLOCKS_PER_CALL - 1);
+        result.append(&mut get_logs_splitting(current_block, to, &fetch).await?);
+        current_block = to + 1;
+    }
+
+    Ok(sort_and_dedup_logs(result))
+}
+
+// Whether the node refused to return the logs because there were too many of them, or the
+// block range was too wide (the wording differs between providers).
+fn is_logs_size_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "response size exceeded",
+        "query returned more than",
+        "too many results",
+        "log response size",
+        "result set too large",
+        "block range is too large",
+        "exceed maximum block range",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+type LogsFuture<'a> = Pin<Box<dyn Future<Output = eyre::Result<Vec<Log>>> + Send + 'a>>;
+
+// Fetches the logs of the [from, to] block range, splitting it in halves (down to single
+// blocks) whenever the node says that the response would be too large.
+fn get_logs_splitting<'a, F, Fut>(from: u64, to: u64, fetch: &'a F) -> LogsFuture<'a>
+where
+    F: Fn(u64, u64) -> Fut + Sync,
+    Fut: Future<Output = Result<Vec<Log>, TransportError>> + Send + 'a,
+{
+    Box::pin(async move {
+        match fetch(from, to).await {
+            Ok(logs) => Ok(logs),
+            Err(err) if from < to && is_logs_size_error(&err.to_string()) => {
+                let middle = from + (to - from) / 2;
+                progress!(
+                    "eth_getLogs response too large for blocks {}-{}, splitting the range",
+                    from,
+                    to
+                );
+                let mut logs = get_logs_splitting(from, middle, fetch).await?;
+                logs.append(&mut get_logs_splitting(middle + 1, to, fetch).await?);
+                Ok(logs)
+            }
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+// Orders the logs by (block number, log index) and drops duplicates - the same log might be
+// returned twice if the scanned windows overlap or the node repeats itself, which would
+// e.g. double count priority transactions.
+pub fn sort_and_dedup_logs(mut logs: Vec<Log>) -> Vec<Log> {
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+    logs.dedup_by_key(|log| (log.block_number, log.log_index));
+    logs
 }
 
 pub fn get_human_name_for<T: AsRef<[u8]>>(entry: T) -> String {
@@ -61,3 +281,83 @@ pub fn get_human_name_for<T: AsRef<[u8]>>(entry: T) -> String {
 
     Ok(Address::from_slice(&bytes.0[12..32]))
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::transports::TransportErrorKind;
+    use std::sync::Mutex;
+
+    fn log_at(block: u64) -> Log {
+        Log {
+            block_number: Some(block),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recognizes_logs_size_errors() {
+        assert!(is_logs_size_error(
+            "server returned an error response: error code -32602: Log response size exceeded."
+        ));
+        assert!(is_logs_size_error("query returned more than 10000 results"));
+        assert!(is_logs_size_error(
+            "eth_getLogs is limited to a 10,000 block range / exceed maximum block range: 10000"
+        ));
+        assert!(!is_logs_size_error("execution reverted"));
+    }
+
+    #[tokio::test]
+    async fn finds_the_first_block_with_code() {
+        let deployed_at = |block: u64| move |at: u64| async move { eyre::Ok(at >= block) };
+
+        for block in [0, 1, 777, 999, 1000] {
+            assert_eq!(
+                first_block_with_code(1000, deployed_at(block))
+                    .await
+                    .unwrap(),
+                Some(block)
+            );
+        }
+        assert_eq!(
+            first_block_with_code(1000, deployed_at(1001))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn splits_ranges_with_too_many_logs() {
+        let requested = Mutex::new(Vec::new());
+        // Ranges wider than 2 blocks are "too large", every block has one log.
+        let fetch = |from: u64, to: u64| {
+            requested.lock().unwrap().push((from, to));
+            async move {
+                if to - from + 1 > 2 {
+                    Err(TransportErrorKind::custom_str("response size exceeded"))
+                } else {
+                    Ok((from..=to).map(log_at).collect())
+                }
+            }
+        };
+
+        let logs = get_logs_splitting(1, 5, &fetch).await.unwrap();
+
+        let blocks: Vec<_> = logs.iter().map(|log| log.block_number.unwrap()).collect();
+        assert_eq!(blocks, vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            *requested.lock().unwrap(),
+            vec![(1, 5), (1, 3), (1, 2), (3, 3), (4, 5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        let fetch = |_: u64, _: u64| async {
+            Err::<Vec<Log>, _>(TransportErrorKind::custom_str("execution reverted"))
+        };
+
+        assert!(get_logs_splitting(1, 5, &fetch).await.is_err());
+    }
+}
@@ -0,0 +1,80 @@
+// Pushing the finished report to a monitoring endpoint (--post-url).
+
+use std::time::Duration;
+
+use alloy::transports::http::Client;
+use colored::Colorize;
+
+const POST_ATTEMPTS: usize = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+// Parses a `Name: value` header argument.
+pub fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid header '{}', expected 'Name: value'", header))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("Invalid header '{}', the name is empty", header));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+// POSTs the serialized report as JSON, retrying failed requests and non-2xx responses
+// a couple of times before giving up.
+pub async fn post_report(
+    url: &str,
+    headers: &[(String, String)],
+    body: Vec<u8>,
+) -> eyre::Result<()> {
+    let client = Client::new();
+    let mut last_error = None;
+
+    for attempt in 1..=POST_ATTEMPTS {
+        let mut request = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body.clone());
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => eyre::eyre!("{} responded with {}", url, response.status()),
+            Err(err) => eyre::eyre!("Failed to post report to {}: {}", url, err),
+        };
+        if attempt < POST_ATTEMPTS {
+            progress!(
+                "{} {} (attempt {} of {}), retrying",
+                "[WARN]".yellow(),
+                error,
+                attempt,
+                POST_ATTEMPTS
+            );
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+        last_error = Some(error);
+    }
+
+    Err(last_error.expect("at least one attempt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header() {
+        assert_eq!(
+            parse_header("Authorization: Bearer abc:def"),
+            Ok(("Authorization".to_string(), "Bearer abc:def".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(parse_header("Authorization").is_err());
+        assert!(parse_header(": value").is_err());
+    }
+}